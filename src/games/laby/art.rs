@@ -136,37 +136,50 @@ pub const RIGHT: &str = "
             /                  \\
 ";
 
-pub const RAT: &str = "
-       _     __,..---''-._                 ';-,
-'    _/_),-\"'             '-.                '\\\\\\
-\\|.-\"'    -_)                 '.                ||
-/'   a   ,                      \\              .'/
-'.___,__/                 .-'    \\_        _.-'.'
-  |\\  \\      \\         /'        _'\"\"\"\"\"\"'_.-'
-     _/;--._, >        |   --.__/ '\"\"\"\"\"\"'
-   (((-'  __//''-......-;\\      )
-        (((-'       __//  '--. /
-                  (((-'    __//
-                         (((-'
+pub const LEFT_RIGHT: &str = "
+                
+            \\                   /
+             \\                 /
+              \\               / 
+              |              / 
+              |______________|
+              |              |
+              |              |
+              |              |
+              |              |
+              |              |
+              |              |
+              |              |
+              |              |
+              |______________|
+              |              |
+              |              | 
+              /              \\ 
+             /                \\ 
+            /                  \\
 ";
 
-pub const BAT: &str = "
-....._      
- '.   ''-.                               .-----.._
-   ',     '-.                          .:      /'
-     :       '\"..                 ..-''       :
-     /   ...--:::'n            n.'::...       :
-     ':''      .' ::          /  '.     ''---..:.
-       '\\    .'  ._:   .-:   ::    '.     .-''
-         :  :    :_\\\\_/: :  .::      '.  /
-         : /      \\-../:/_.'-'         \\ :
-         :: _.._  q' p ' /'             \\|
-         :-'    ''(_. ..-----hh''''''/-._:
-                     ':      ''     /     '
-                     E:            /
-                      :          _/
-                      :    _..-''
-                      l--''
+pub const DEAD_END: &str = "
+                
+            \\                  /
+             \\                /
+              \\              / 
+               \\            / 
+                \\__________/   
+                |          |
+                |          |
+                |          |
+                |          |
+                |          |
+                |          |
+                |          |
+                |          |
+                |__________|
+                /          \\  
+               /            \\ 
+              /              \\ 
+             /                \\ 
+            /                  \\
 ";
 
 pub const CHEST: &str = "
@@ -206,6 +219,22 @@ pub const SWORD: &str = "
               \\>
 ";
 
+pub const POTION: &str = "
+                     _____
+                    `.___,'
+                     (___)
+                     <   >
+                      ) (
+                     /`-.\\
+                    /     \\
+                   / _    _\\
+                  :,' `-.' `:
+                  |         |
+                  :         ;
+                   \\       /
+                    `.___.'
+";
+
 pub const KNIGHT: &str = "
           / \
           | |
@@ -228,22 +257,65 @@ pub const KNIGHT: &str = "
                  '-'
 ";
 
-pub const BOSS: &str = "
-              __.......__
-            .-:::::::::::::-.
-          .:::''':::::::''':::.
-        .:::'     ':::'     ':::. 
-   .'\\  ::'   ^^^  ':'  ^^^   '::  /'.
-  :   \\ ::   _.__       __._   :: /   ;
- :     \\': .' ___\\     /___ '. :'/     ; 
-:       /\\   (_|_)\\   /(_|_)   /\\       ;
-:      / .\\   __.' ) ( '.__   /. \\      ;
-:      \\ (        {   }        ) /      ; 
- :      '-(     .  ^\"^  .     )-'      ;
-  '.       \\  .'<'-._.-'>'.  /       .'
-    '.      \\    \\;'.';/    /      .'
-      '._    '-._       _.-'    _.'
-       .''-.__ .''-._.-''. __.-''.
-     .'       '.         .'       '.
-   .'           '-.   .-'           '.
+pub const MERCHANT: &str = "
+                     _______
+                    /       \\
+                   /  _   _  \\
+                  |  (o) (o)  |
+                  |     ^     |
+                   \\  \\___/  /
+                    \\_______/
+                  ___|     |___
+                 /   |  $  |   \\
+                /    |_____|    \\
+               |   ___|   |___   |
+               |__|  [_____]  |__|
+";
+
+pub const UNKNOWN_ENEMY: &str = "
+                    .-~~~~-.
+                  .'        '.
+                 /   O    O   \\
+                :              :
+                :    .----.    :
+                 \\  '      '  /
+                  '.        .'
+                 .-'~~~~~~~~'-.
+                /   ?  ?  ?    \\
+               |                |
+";
+pub const RIDDLE_DOOR: &str = "
+                  ___________________
+                 |  _______________  |
+                 | |   .-\"\"\"\"\"-.   | |
+                 | |  /  _   _  \\  | |
+                 | | |  (o) (o)  | | |
+                 | |  \\    ^    /  | |
+                 | |   '. === .'   | |
+                 | |     '---'     | |
+                 | |               | |
+                 | |             o | |
+                 | |               | |
+                 |_|_______________|_|
+";
+pub const TRAP: &str = "
+          ________________________________________
+         /                                        \\
+        /      .                      .            \\
+       /              ______________                \\
+      /       .      /______________\\        .       \\
+     /               |______________|                 \\
+    /____________________________________________________\\
+";
+pub const GATE: &str = "
+                 ___________________________
+                |  |  |  |  |  |  |  |  |  |
+                |  |  |  |  |  |  |  |  |  |
+                |==|==|==|==|==|==|==|==|==|
+                |  |  |  |  |  |  |  |  |  |
+                |  |  |  |  [__]  |  |  |  |
+                |  |  |  |  |  |  |  |  |  |
+                |==|==|==|==|==|==|==|==|==|
+                |  |  |  |  |  |  |  |  |  |
+                |__|__|__|__|__|__|__|__|__|
 ";
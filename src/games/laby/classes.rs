@@ -0,0 +1,79 @@
+use serde::Deserialize;
+
+use super::{data::LevelBonus, effects::StatusEffect};
+
+/// What the player picks before entering the labyrinth
+#[derive(Debug, Deserialize, Clone)]
+pub struct PlayerClass {
+    pub name: String,
+    pub description: String,
+    // stats at level 1
+    pub max_health: f32,
+    pub damages: f32,
+    #[serde(default)]
+    pub gold: usize,
+    // names of items of the items list, given at the start
+    #[serde(default)]
+    pub items: Vec<String>,
+    pub ability: ClassAbility,
+    // stats gained when reaching level 2, 3...
+    // the last value is used for all the next levels
+    pub level_path: Vec<LevelBonus>,
+}
+
+/// The signature move of a class, used in fights
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClassAbility {
+    // typed in the console to use it
+    pub command: String,
+    pub name: String,
+    pub description: String,
+    // multiplies the damages of the player
+    #[serde(default)]
+    pub damages: f32,
+    #[serde(default)]
+    pub heal: f32,
+    // goes through the guard and the shield of the enemy
+    #[serde(default)]
+    pub piercing: bool,
+    // given to the enemy
+    #[serde(default)]
+    pub effect: Option<StatusEffect>,
+    // given to the player
+    #[serde(default)]
+    pub self_effect: Option<StatusEffect>,
+    // fights turns to wait before using it again
+    pub cooldown: usize,
+}
+
+impl PlayerClass {
+    /// The bonus for reaching this level
+    pub fn get_level_bonus(&self, level: usize) -> Option<&LevelBonus> {
+        self.level_path
+            .get(level.saturating_sub(2))
+            .or_else(|| self.level_path.last())
+    }
+
+    pub fn to_display(&self) -> String {
+        format!(
+            "{} - {} HP, {} dmg\n   {}\n   Ability: '{}' - {}",
+            self.name,
+            self.max_health,
+            self.damages,
+            self.description,
+            self.ability.command,
+            self.ability.description
+        )
+    }
+
+    /// Finds a class by its number in the list or by its name
+    pub fn find<'a>(classes: &'a [PlayerClass], query: &str) -> Option<&'a PlayerClass> {
+        if let Ok(number) = query.parse::<usize>() {
+            return number.checked_sub(1).and_then(|index| classes.get(index));
+        }
+
+        classes
+            .iter()
+            .find(|class| class.name.to_lowercase() == query.to_lowercase())
+    }
+}
@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use rand::Rng;
+use rand::{prelude::SliceRandom, Rng};
 
 use crate::{
     console::{
@@ -7,34 +7,61 @@ use crate::{
         ConsoleData,
     },
     games::ConsoleGamesData,
-    npcs::NPCsResource,
+    npcs::{Interaction, NPCsResource},
     vulnerability::VulnerabilityResource,
 };
 
 use super::{
+    classes::PlayerClass,
+    companions::{Companion, LEAVE_THRESHOLD},
     data::{
-        GameState, LabyrinthData, LabyrinthResourceFile, Movement, PlayerActions, PlayerStats,
-        RoomType,
+        GameState, LabyrinthData, LabyrinthResourceFile, LabyrinthRng, Movement, PlayerActions,
+        PlayerStats, RoomType, RunMode,
     },
-    enemies::EnemyType,
-    game::new_turn,
+    dialogues::{Dialogue, DialogueEffect, DialogueState},
+    effects::EffectType,
+    enemies::{Enemy, EnemyAction},
+    game::{clear_room, descend, end_run, move_player, start_exploring},
+    items::{Item, ItemType, CHEST_GOLD, INVENTORY_SIZE},
+    puzzles::RoomOutcome,
+    skills::SkillKind,
+    summary::{LabyrinthHistory, RunEnding},
 };
 
+// the number of pages of 'help'
+const HELP_PAGES: usize = 3;
+
 pub fn commands_handler(
     mut cmd_reader: EventReader<EnteredConsoleCommandEvent>,
     mut console_writer: EventWriter<PrintConsoleEvent>,
     mut cg_data: ResMut<ConsoleGamesData>,
     mut data: ResMut<ConsoleData>,
     mut laby_data: ResMut<LabyrinthData>,
-    mut laby_res: ResMut<LabyrinthResourceFile>,
+    laby_res: Res<LabyrinthResourceFile>,
     mut vuln_res: ResMut<VulnerabilityResource>,
     mut player: ResMut<PlayerStats>,
     npc_res: Res<NPCsResource>,
+    mut laby_rng: ResMut<LabyrinthRng>,
+    mut history: ResMut<LabyrinthHistory>,
 ) {
-    for EnteredConsoleCommandEvent(cmd) in cmd_reader.iter() {
+    // the commands typed during the walk wait for the next room,
+    // the player can still ragequit right away
+    let mut commands = std::mem::take(&mut laby_data.queued_commands);
+    commands.extend(
+        cmd_reader
+            .iter()
+            .map(|EnteredConsoleCommandEvent(cmd)| cmd.clone()),
+    );
+
+    for cmd in commands.iter() {
+        if laby_data.walking.is_some() && cmd.trim() != "ragequit" {
+            laby_data.queued_commands.push(cmd.clone());
+            continue;
+        }
+
         // Don't do anything if the string is empty
         if cmd.is_empty() {
-            return;
+            continue;
         }
 
         let args: Vec<&str> = cmd.trim().split(' ').collect();
@@ -49,16 +76,33 @@ pub fn commands_handler(
         match args[0] {
             "clear" => data.messages.clear(),
             "help" => {
-                if args.len() == 1 {
-                    console_writer.send(PrintConsoleEvent(display_help(1)));
-                } else {
-                    console_writer.send(PrintConsoleEvent(display_help(args[1].parse().unwrap())))
+                let page = match args.get(1) {
+                    Some(page) => page.parse().ok(),
+                    None => Some(1),
+                };
+                match page {
+                    Some(page) if (1..=HELP_PAGES).contains(&page) => {
+                        console_writer.send(PrintConsoleEvent(display_help(page)))
+                    }
+                    _ => console_writer.send(PrintConsoleEvent(format!(
+                        "Usage: help [page], valid: (1-{})",
+                        HELP_PAGES
+                    ))),
                 }
             }
             "ragequit" => {
                 console_writer.send(PrintConsoleEvent("Quitting Labyrinth...".to_string()));
+                // a lost run was already summed up
+                if laby_data.steps_number > 0 && player.health > 0.0 {
+                    console_writer.send(PrintConsoleEvent(end_run(
+                        &mut laby_data,
+                        &player,
+                        &laby_rng,
+                        &mut history,
+                        RunEnding::Ragequit,
+                    )));
+                }
                 laby_data.reset();
-                laby_res.reset();
                 player.reset();
                 cg_data.ragequit(&mut vuln_res);
             }
@@ -70,48 +114,185 @@ pub fn commands_handler(
             "infos" => {
                 laby_data.has_shown_turn_infos = false;
             }
+            "map" => {
+                if laby_data.game_state == GameState::Exploring {
+                    console_writer.send(PrintConsoleEvent(display_map(&laby_data)));
+                } else {
+                    console_writer.send(PrintConsoleEvent(
+                        "You haven't entered the labyrinth yet...".to_string(),
+                    ));
+                }
+            }
             "continue" => {
                 if laby_data.game_state == GameState::Tutorial {
                     laby_data.has_shown_turn_infos = false;
                     laby_data.wait_for_continue = false;
 
                     if laby_data.tutorial_page + 1 == laby_res.tutorial.len() {
-                        laby_data.game_state = GameState::Exploring;
-                        new_turn(&mut laby_data, &laby_res, &mut player, &npc_res);
+                        start_exploring(
+                            &mut laby_data,
+                            &laby_res,
+                            &mut player,
+                            &npc_res,
+                            &mut laby_rng,
+                            &cg_data,
+                        );
                     } else {
                         laby_data.tutorial_page += 1;
                     }
+                } else if laby_data.game_state == GameState::LevelUp {
+                    laby_data.game_state = GameState::Exploring;
+                    laby_data.has_shown_turn_infos = false;
+                    laby_data.wait_for_continue = false;
                 } else {
                     console_writer.send(PrintConsoleEvent(
                         "There is nothing to continue...".to_string(),
                     ));
                 }
             }
+            "levelup" => {
+                if laby_data.game_state != GameState::Exploring
+                    || laby_data.room_type == RoomType::Enemy
+                {
+                    console_writer.send(PrintConsoleEvent(
+                        "This is not the time to think about it...".to_string(),
+                    ));
+                    continue;
+                }
+
+                laby_data.game_state = GameState::LevelUp;
+                laby_data.has_shown_turn_infos = false;
+                laby_data.wait_for_continue = false;
+            }
+            "spend" | "learn" => {
+                if args.len() == 1 {
+                    console_writer.send(PrintConsoleEvent(format!(
+                        "Usage: {} <{}>",
+                        args[0],
+                        if args[0] == "spend" { "stat" } else { "skill" }
+                    )));
+                    continue;
+                }
+                if laby_data.room_type == RoomType::Enemy {
+                    console_writer.send(PrintConsoleEvent("Finish the fight first...".to_string()));
+                    continue;
+                }
+
+                let name = args[1..].join(" ");
+                let result = if args[0] == "spend" {
+                    spend_point(&mut player, &laby_res, &name)
+                } else {
+                    learn_skill(&mut player, &laby_res, &name)
+                };
+                match result {
+                    Ok(msg) => {
+                        console_writer.send(PrintConsoleEvent(msg.clone()));
+                        laby_data.status_message = msg;
+                        // back to the labyrinth once every point is spent
+                        if player.skill_points == 0 && laby_data.game_state == GameState::LevelUp {
+                            laby_data.game_state = GameState::Exploring;
+                        }
+                        laby_data.has_shown_turn_infos = false;
+                        laby_data.wait_for_continue = false;
+                    }
+                    Err(msg) => console_writer.send(PrintConsoleEvent(msg)),
+                }
+            }
+            "choose" => {
+                if laby_data.game_state != GameState::CharacterCreation {
+                    console_writer.send(PrintConsoleEvent(
+                        "Your class is already chosen...".to_string(),
+                    ));
+                    continue;
+                }
+                if args.len() == 1 {
+                    console_writer.send(PrintConsoleEvent("Usage: choose <class>".to_string()));
+                    continue;
+                }
+
+                let class = match PlayerClass::find(&laby_res.classes, &args[1..].join(" ")) {
+                    Some(class) => class.clone(),
+                    None => {
+                        console_writer
+                            .send(PrintConsoleEvent("There is no such class...".to_string()));
+                        continue;
+                    }
+                };
+                player.choose_class(&class, &laby_res.items);
+                laby_data.run_stats.class = Some(class.name.clone());
+
+                laby_data.has_shown_turn_infos = false;
+                laby_data.wait_for_continue = false;
+                start_exploring(
+                    &mut laby_data,
+                    &laby_res,
+                    &mut player,
+                    &npc_res,
+                    &mut laby_rng,
+                    &cg_data,
+                );
+                let msg = format!(
+                    "You are now a {}!\n{}",
+                    class.name, laby_data.status_message
+                );
+                laby_data.status_message = msg;
+            }
             "skip" => {
                 if laby_data.game_state == GameState::Exploring
                     && ((laby_data.room_type == RoomType::Enemy
-                        && laby_data.enemy.kind != EnemyType::Boss)
-                        || laby_data.room_type == RoomType::Item)
+                        && laby_data.position != laby_data.maze.exit)
+                        || laby_data.room_type == RoomType::Item
+                        || laby_data.room_type == RoomType::Merchant)
                 {
-                    console_writer.send(PrintConsoleEvent("Skipping room...".to_string()));
-                    laby_data.status_message = "Skipping room...".to_string();
-                    new_turn(&mut laby_data, &laby_res, &mut player, &npc_res);
+                    let mut msg = "Skipping room...".to_string();
+                    // running from a fight doesn't impress anyone
+                    if laby_data.room_type == RoomType::Enemy {
+                        for note in disappoint_party(&mut player, &mut vuln_res) {
+                            msg.push_str(&format!("\n{}", note));
+                        }
+                    }
+                    console_writer.send(PrintConsoleEvent(msg.clone()));
+                    laby_data.status_message = msg;
+                    clear_room(&mut laby_data, &laby_res, &mut laby_rng);
                     laby_data.has_shown_turn_infos = false;
                     laby_data.wait_for_continue = false;
+                } else if laby_data.room_type == RoomType::Riddle
+                    || laby_data.room_type == RoomType::Trap
+                {
+                    // no way past them without consequences
+                    let outcome = if laby_data.room_type == RoomType::Riddle {
+                        &laby_res.riddles[laby_data.puzzle].failure
+                    } else {
+                        &laby_res.traps[laby_data.puzzle].failure
+                    };
+                    let msg = end_puzzle(
+                        &mut laby_data,
+                        &laby_res,
+                        &mut player,
+                        &mut laby_rng,
+                        outcome,
+                    );
+                    console_writer.send(PrintConsoleEvent(msg));
                 } else if laby_data.room_type == RoomType::Npc {
-                    let msg=  "The NPC feels insulted you skipped him/her... He/she hates you".to_string();
+                    let note = interact_with_npc(&mut laby_data, &mut vuln_res, Interaction::Skip);
+                    let msg = format!("{} feels ignored...\n{}", laby_data.npc.username, note);
                     console_writer.send(PrintConsoleEvent(msg.clone()));
                     laby_data.status_message = msg;
-                    vuln_res.enemies.push(laby_data.npc.username.clone());
-                    
+
                     laby_data.has_shown_turn_infos = false;
                     laby_data.wait_for_continue = false;
-                    new_turn(&mut laby_data, &laby_res, &mut player, &npc_res);
-                } else if laby_data.game_state == GameState::Tutorial  {
-                    laby_data.game_state = GameState::Exploring;
+                    clear_room(&mut laby_data, &laby_res, &mut laby_rng);
+                } else if laby_data.game_state == GameState::Tutorial {
                     laby_data.has_shown_turn_infos = false;
                     laby_data.wait_for_continue = false;
-                    new_turn(&mut laby_data, &laby_res, &mut player, &npc_res);
+                    start_exploring(
+                        &mut laby_data,
+                        &laby_res,
+                        &mut player,
+                        &npc_res,
+                        &mut laby_rng,
+                        &cg_data,
+                    );
                 } else {
                     console_writer
                         .send(PrintConsoleEvent("You can't skip this room...".to_string()));
@@ -123,47 +304,158 @@ pub fn commands_handler(
                         "You specified no direction...".to_string(),
                     ));
                     console_writer.send(PrintConsoleEvent(
-                        "Usage: go <direction>, valid: (FORWARD, LEFT, RIGHT)".to_string(),
+                        "Usage: go <direction>, valid: (FORWARD, LEFT, RIGHT, BACK)".to_string(),
                     ));
-                    return;
+                    continue;
                 }
 
-                if let Some(movement) = Movement::from_string(args[1]) {
-                    if laby_data.next_directions.can_go_direction(movement) {
-                        new_turn(&mut laby_data, &laby_res, &mut player, &npc_res);
-                        laby_data.has_shown_turn_infos = false;
-                        laby_data.wait_for_continue = false;
-                    } else {
-                        console_writer.send(PrintConsoleEvent(
-                            "There is no path in this direction...".to_string(),
-                        ));
+                if laby_data.game_state != GameState::Exploring {
+                    console_writer.send(PrintConsoleEvent(
+                        "Type 'continue' to enter the labyrinth first...".to_string(),
+                    ));
+                    continue;
+                }
+
+                let movement = Movement::from_string(args[1]);
+                // a closed gate can only be left the way the player came
+                let is_turning_back = laby_data.room_type == RoomType::Gate
+                    && matches!(movement, Some(Movement::Back));
+                if laby_data.room_type != RoomType::Corridor && !is_turning_back {
+                    console_writer.send(PrintConsoleEvent(
+                        "You have to deal with this room before leaving...".to_string(),
+                    ));
+                    continue;
+                }
+
+                if let Some(movement) = movement {
+                    let gate = (laby_data.position, laby_data.puzzle);
+                    match move_player(&mut laby_data, &mut player, movement) {
+                        // the room is shown once the walk is over
+                        Ok(()) => {
+                            if is_turning_back && !laby_data.closed_gates.contains(&gate) {
+                                laby_data.closed_gates.push(gate);
+                            }
+                            laby_data.wait_for_continue = false;
+                        }
+                        Err(msg) => console_writer.send(PrintConsoleEvent(msg)),
                     }
-                    return;
+                    continue;
                 } else {
                     console_writer.send(PrintConsoleEvent(
                         "Please enter a valid direction...".to_string(),
                     ));
                     console_writer.send(PrintConsoleEvent(
-                        "Usage: go <direction>, valid: (FORWARD, LEFT, RIGHT)".to_string(),
+                        "Usage: go <direction>, valid: (FORWARD, LEFT, RIGHT, BACK)".to_string(),
                     ));
                 }
             }
+            "descend" => {
+                if laby_data.game_state != GameState::Exploring
+                    || laby_data.room_type != RoomType::Corridor
+                    || laby_data.position != laby_data.maze.exit
+                    || !laby_data.is_stairs_open
+                {
+                    console_writer
+                        .send(PrintConsoleEvent("There are no stairs here...".to_string()));
+                    continue;
+                }
+
+                descend(
+                    &mut laby_data,
+                    &laby_res,
+                    &mut player,
+                    &npc_res,
+                    &mut laby_rng,
+                );
+                laby_data.has_shown_turn_infos = false;
+                laby_data.wait_for_continue = false;
+            }
+            ability if matches!(&player.class, Some(class) if class.ability.command == ability) => {
+                if laby_data.room_type != RoomType::Enemy {
+                    console_writer.send(PrintConsoleEvent(
+                        "There is nobody to use it on here...".to_string(),
+                    ));
+                    continue;
+                }
+                if player.effects.has(EffectType::Stun) {
+                    let msg = String::from("You are stunned and can't do anything this turn!");
+                    console_writer.send(PrintConsoleEvent(msg.clone()));
+                    laby_data.status_message = msg;
+                    player.action = PlayerActions::Attack;
+
+                    ai_turn(&mut player, &mut laby_data, &mut laby_rng);
+                    continue;
+                }
+                if player.ability_cooldown > 0 {
+                    console_writer.send(PrintConsoleEvent(format!(
+                        "You need to catch your breath, {} more turns...",
+                        player.ability_cooldown
+                    )));
+                    continue;
+                }
+
+                let msg = use_ability(&mut player, &mut laby_data);
+                console_writer.send(PrintConsoleEvent(msg.clone()));
+                laby_data.status_message = msg;
+                player.action = PlayerActions::Attack;
+
+                ai_turn(&mut player, &mut laby_data, &mut laby_rng);
+            }
+            "attack" | "prepare" | "protect"
+                if laby_data.room_type == RoomType::Enemy
+                    && player.effects.has(EffectType::Stun) =>
+            {
+                let msg = String::from("You are stunned and can't do anything this turn!");
+                console_writer.send(PrintConsoleEvent(msg.clone()));
+                laby_data.status_message = msg;
+                player.action = PlayerActions::Attack;
+
+                ai_turn(&mut player, &mut laby_data, &mut laby_rng);
+            }
             "attack" => {
                 if laby_data.room_type == RoomType::Enemy {
                     // player phase
-                    let damages = {
+                    let mut damages = {
                         if player.last_action == PlayerActions::Prepare {
-                            player.damages * 2.5
+                            player.get_damages() * 2.5
                         } else {
-                            player.damages
+                            player.get_damages()
                         }
                     };
-                    let atk_msg = format!("Attacking the enemy for {} damage", damages);
+                    let mut atk_msg = format!(
+                        "Attacking the {} for {} damage",
+                        laby_data.enemy.name, damages
+                    );
+                    if laby_data.enemy.intention == EnemyAction::Defend {
+                        damages *= 0.5;
+                        atk_msg = format!(
+                            "The {} blocks half of your attack, {} damage",
+                            laby_data.enemy.name, damages
+                        );
+                    }
+                    if laby_data.enemy.effects.has(EffectType::Shield) {
+                        damages = laby_data.enemy.effects.absorb(damages);
+                        atk_msg.push_str(&format!(" (shielded, {} left)", damages));
+                    }
                     console_writer.send(PrintConsoleEvent(atk_msg.clone()));
                     laby_data.status_message = atk_msg.clone();
                     laby_data.enemy.health -= damages;
+                    laby_data.run_stats.damages_dealt += damages;
+                    player.action = PlayerActions::Attack;
+
+                    // some weapons hurt more than the blow itself
+                    if let Some(effect) = player.weapon.as_ref().and_then(|weapon| weapon.effect) {
+                        if laby_data.enemy.effects.apply(effect) {
+                            let msg = format!(
+                                "\nThe {} is {}!",
+                                laby_data.enemy.name,
+                                effect.kind.to_display_str().to_lowercase()
+                            );
+                            laby_data.status_message.push_str(&msg);
+                        }
+                    }
 
-                    ai_turn(&mut player, &mut laby_data);
+                    ai_turn(&mut player, &mut laby_data, &mut laby_rng);
                 } else {
                     console_writer.send(PrintConsoleEvent(
                         "You punch... uh... the wall!".to_string(),
@@ -180,7 +472,7 @@ pub fn commands_handler(
                     laby_data.status_message = msg.clone();
                     player.action = PlayerActions::Prepare;
 
-                    ai_turn(&mut player, &mut laby_data);
+                    ai_turn(&mut player, &mut laby_data, &mut laby_rng);
                 } else {
                     console_writer.send(PrintConsoleEvent(
                         "There is no need to prepare an attack here...".to_string(),
@@ -194,7 +486,7 @@ pub fn commands_handler(
                     laby_data.status_message = msg.clone();
                     player.action = PlayerActions::Protect;
 
-                    ai_turn(&mut player, &mut laby_data);
+                    ai_turn(&mut player, &mut laby_data, &mut laby_rng);
                 } else {
                     console_writer.send(PrintConsoleEvent(
                         "You protect yourself from the strange liquid coming from the ceiling on your head...".to_string(),
@@ -202,20 +494,83 @@ pub fn commands_handler(
                 }
             }
             "talk" => {
+                if laby_data.room_type != RoomType::Npc {
+                    console_writer.send(PrintConsoleEvent(
+                        "You try to talk to yourself and gained nothing but loneliness.."
+                            .to_string(),
+                    ));
+                    continue;
+                }
+                if laby_data.dialogue.is_some() {
+                    console_writer.send(PrintConsoleEvent(
+                        "Answer by typing the number of your choice".to_string(),
+                    ));
+                    continue;
+                }
+
+                match Dialogue::get_random_for(
+                    &laby_res.dialogues,
+                    laby_data.npc.sprite_id,
+                    &mut laby_rng.rng,
+                ) {
+                    Some(dialogue) => {
+                        laby_data.dialogue = Some(DialogueState { dialogue, node: 0 });
+                        interact_with_npc(&mut laby_data, &mut vuln_res, Interaction::Talk);
+                    }
+                    None => {
+                        laby_data.status_message =
+                            format!("{} has nothing to say to you...", laby_data.npc.username);
+                        clear_room(&mut laby_data, &laby_res, &mut laby_rng);
+                    }
+                }
+
+                laby_data.has_shown_turn_infos = false;
+                laby_data.wait_for_continue = false;
+            }
+            answer
+                if laby_data.dialogue.is_some()
+                    && (answer == "say" || answer.parse::<usize>().is_ok()) =>
+            {
+                let number = if answer == "say" {
+                    args.get(1).copied().unwrap_or_default()
+                } else {
+                    answer
+                };
+
+                match choose_answer(
+                    &mut laby_data,
+                    &laby_res,
+                    &mut player,
+                    &mut vuln_res,
+                    &mut laby_rng,
+                    number,
+                ) {
+                    Ok(msg) => {
+                        console_writer.send(PrintConsoleEvent(msg.clone()));
+                        laby_data.status_message = msg;
+                        laby_data.has_shown_turn_infos = false;
+                        laby_data.wait_for_continue = false;
+                    }
+                    Err(msg) => console_writer.send(PrintConsoleEvent(msg)),
+                }
+            }
+            "insult" => {
                 if laby_data.room_type == RoomType::Npc {
-                    let will_give_boon = rand::thread_rng().gen_ratio(2, 10);
-                    let feel_insulted = rand::thread_rng().gen_ratio(5, 10);
+                    let will_give_boon = laby_rng.rng.gen_ratio(9, 10);
 
-                    let msg: String;
+                    let mut msg: String;
                     if will_give_boon {
-                        msg = "The NPC was happy you talked with him and wants to give you something".to_string();
+                        msg = format!(
+                            "You become stronger by insulting this NPC...\n{}",
+                            interact_with_npc(&mut laby_data, &mut vuln_res, Interaction::Insult)
+                        );
                         player.health += 1.0;
                         player.damages += 1.0;
-                    } else if feel_insulted {
-                        msg = "The NPC feels insulted by your words... He/she hates you".to_string();
-                        vuln_res.enemies.push(laby_data.npc.username.clone());
                     } else {
-                        msg = "I think you just made a friend! Pretty nice huh?".to_string();
+                        msg = "The NPC just doesn't care, he just goes away".to_string();
+                    }
+                    for note in disappoint_party(&mut player, &mut vuln_res) {
+                        msg.push_str(&format!("\n{}", note));
                     }
 
                     laby_data.has_shown_turn_infos = false;
@@ -223,58 +578,281 @@ pub fn commands_handler(
 
                     console_writer.send(PrintConsoleEvent(msg.clone()));
                     laby_data.status_message = msg.clone();
-                    new_turn(&mut laby_data, &laby_res, &mut player, &npc_res);
+                    clear_room(&mut laby_data, &laby_res, &mut laby_rng);
                 } else {
                     console_writer.send(PrintConsoleEvent(
-                        "You try to talk to yourself and gained nothing but loneliness.."
-                            .to_string(),
+                        "You insult the void and are reminded of your loneliness".to_string(),
                     ));
                 }
             }
-            "insult" => {
-                if laby_data.room_type == RoomType::Npc {
-                    let will_give_boon = rand::thread_rng().gen_ratio(9, 10);
+            "apologize" | "sorry" => {
+                if laby_data.room_type != RoomType::Npc {
+                    console_writer.send(PrintConsoleEvent(
+                        "There is nobody to apologize to...".to_string(),
+                    ));
+                    continue;
+                }
 
-                    let msg: String;
-                    if will_give_boon {
-                        msg = "You become stronger by insulting this NPC. But you've made a new enemy...".to_string();
-                        player.health += 1.0;
-                        player.damages += 1.0;
-                        
-                        // adding the npc to the list of enemies
-                        vuln_res.enemies.push(laby_data.npc.username.clone());
-                    } else {
-                        msg = "The NPC just doesn't care, he just goes away".to_string();
-                    }
+                let username = laby_data.npc.username.clone();
+                let msg = if vuln_res.relationships.get(&username) >= 0 {
+                    format!("{} doesn't know what you're sorry about", username)
+                } else {
+                    format!(
+                        "You apologize to {}, they accept it and go away\n{}",
+                        username,
+                        interact_with_npc(&mut laby_data, &mut vuln_res, Interaction::Apology)
+                    )
+                };
 
+                console_writer.send(PrintConsoleEvent(msg.clone()));
+                laby_data.status_message = msg;
+                laby_data.has_shown_turn_infos = false;
+                laby_data.wait_for_continue = false;
+                clear_room(&mut laby_data, &laby_res, &mut laby_rng);
+            }
+            "accept" => match laby_data.offered_quest.take() {
+                Some(quest) => {
+                    let msg = format!(
+                        "You accept the quest of {}, type 'quests' to follow it",
+                        quest.giver
+                    );
+                    laby_data.journal.on_npc(&quest.giver, "gave you a quest");
+                    laby_data.add_log(&format!("Accepted the quest of {}", quest.giver));
+                    laby_data.quests.push(quest);
+                    console_writer.send(PrintConsoleEvent(msg.clone()));
+                    laby_data.status_message = msg;
                     laby_data.has_shown_turn_infos = false;
                     laby_data.wait_for_continue = false;
+                }
+                None => console_writer.send(PrintConsoleEvent(
+                    "Nobody is asking you anything...".to_string(),
+                )),
+            },
+            "quests" => {
+                console_writer.send(PrintConsoleEvent(display_quests(&laby_data)));
+            }
+            "history" => {
+                let msg = match args.get(1).copied() {
+                    None => history.to_display(),
+                    Some("endless") => history.display_leaderboard(RunMode::Endless),
+                    Some("speedrun") => history.display_leaderboard(RunMode::Speedrun),
+                    Some(_) => "Usage: history [endless|speedrun]".to_string(),
+                };
+                console_writer.send(PrintConsoleEvent(msg));
+            }
+            "journal" => {
+                let page = args.get(1).copied().unwrap_or("bestiary");
+                match display_journal(&laby_data, &vuln_res, page) {
+                    Some(journal) => console_writer.send(PrintConsoleEvent(journal)),
+                    None => console_writer.send(PrintConsoleEvent(
+                        "Usage: journal <bestiary|npcs|log>".to_string(),
+                    )),
+                }
+            }
+            "invite" => {
+                if laby_data.room_type != RoomType::Npc {
+                    console_writer.send(PrintConsoleEvent(
+                        "There is nobody to invite here...".to_string(),
+                    ));
+                    continue;
+                }
 
-                    console_writer.send(PrintConsoleEvent(msg.clone()));
-                    laby_data.status_message = msg.clone();
-                    new_turn(&mut laby_data, &laby_res, &mut player, &npc_res);
+                let username = laby_data.npc.username.clone();
+                let msg = if player.party.len() >= laby_res.rules.companion.party_size {
+                    "Your party is full, 'dismiss' someone first".to_string()
+                } else if !vuln_res.relationships.is_friend(&username) {
+                    format!(
+                        "{} doesn't trust you enough to follow you ({})",
+                        username,
+                        vuln_res.relationships.to_display(&username)
+                    )
                 } else {
-                    console_writer.send(PrintConsoleEvent("You insult the void and are reminded of your loneliness".to_string()));
+                    let companion =
+                        Companion::new(&username, player.level, &laby_res.rules.companion);
+                    player.party.push(companion);
+                    laby_data.journal.on_npc(&username, "joined your party");
+                    laby_data.add_log(&format!("{} joined your party", username));
+                    clear_room(&mut laby_data, &laby_res, &mut laby_rng);
+                    format!("{} joins your party!", username)
+                };
+
+                console_writer.send(PrintConsoleEvent(msg.clone()));
+                laby_data.status_message = msg;
+                laby_data.has_shown_turn_infos = false;
+                laby_data.wait_for_continue = false;
+            }
+            "dismiss" => {
+                let query = args[1..].join(" ").to_lowercase();
+                match player
+                    .party
+                    .iter()
+                    .position(|companion| companion.username.to_lowercase() == query)
+                {
+                    Some(index) => {
+                        let companion = player.party.remove(index);
+                        laby_data
+                            .journal
+                            .on_npc(&companion.username, "was dismissed from your party");
+                        let msg = format!("{} leaves your party", companion.username);
+                        console_writer.send(PrintConsoleEvent(msg.clone()));
+                        laby_data.status_message = msg;
+                        laby_data.has_shown_turn_infos = false;
+                        laby_data.wait_for_continue = false;
+                    }
+                    None => console_writer.send(PrintConsoleEvent(
+                        "Usage: dismiss <username of a companion>".to_string(),
+                    )),
                 }
             }
-            "loot" => {
-                if laby_data.room_type == RoomType::Item {
-                    let will_give_boon = rand::thread_rng().gen_ratio(7, 10);
+            "give" => {
+                if laby_data.room_type != RoomType::Npc {
+                    console_writer.send(PrintConsoleEvent(
+                        "There is nobody to give something to...".to_string(),
+                    ));
+                    continue;
+                }
 
-                    let msg: String;
-                    if will_give_boon {
-                        msg = "You managed to find a good item and gained damages".to_string();
-                        player.damages += 0.5;
-                    } else {
-                        msg = "Ah crap its too rusty... maybe next time?".to_string();
+                let index = match player.find_item(&args[1..].join(" ")) {
+                    Some(index) => index,
+                    None => {
+                        console_writer.send(PrintConsoleEvent(
+                            "Usage: give <item>, type 'inventory' to see your items".to_string(),
+                        ));
+                        continue;
+                    }
+                };
+
+                let item = player.inventory.remove(index);
+                let note = interact_with_npc(&mut laby_data, &mut vuln_res, Interaction::Gift);
+                let msg = format!(
+                    "You give the {} to {}\n{}",
+                    item.name, laby_data.npc.username, note
+                );
+                console_writer.send(PrintConsoleEvent(msg.clone()));
+                laby_data.status_message = msg;
+                laby_data.has_shown_turn_infos = false;
+                laby_data.wait_for_continue = false;
+            }
+            "answer" => {
+                if laby_data.room_type != RoomType::Riddle {
+                    console_writer.send(PrintConsoleEvent(
+                        "Nobody asked you anything...".to_string(),
+                    ));
+                    continue;
+                }
+                if args.len() == 1 {
+                    console_writer
+                        .send(PrintConsoleEvent("Usage: answer <your answer>".to_string()));
+                    continue;
+                }
+
+                let riddle = &laby_res.riddles[laby_data.puzzle];
+                let outcome = if riddle.is_answer(&args[1..].join(" ")) {
+                    &riddle.success
+                } else {
+                    &riddle.failure
+                };
+                let msg = end_puzzle(
+                    &mut laby_data,
+                    &laby_res,
+                    &mut player,
+                    &mut laby_rng,
+                    outcome,
+                );
+                console_writer.send(PrintConsoleEvent(msg));
+            }
+            "inspect" => {
+                if laby_data.room_type != RoomType::Trap {
+                    console_writer.send(PrintConsoleEvent(
+                        "You look around... Nothing special here.".to_string(),
+                    ));
+                    continue;
+                }
+
+                laby_data.is_trap_inspected = true;
+                let hint = laby_res.traps[laby_data.puzzle].hint.clone();
+                console_writer.send(PrintConsoleEvent(hint));
+            }
+            "disarm" => {
+                if laby_data.room_type != RoomType::Trap {
+                    console_writer.send(PrintConsoleEvent(
+                        "There is nothing to disarm here...".to_string(),
+                    ));
+                    continue;
+                }
+
+                let trap = &laby_res.traps[laby_data.puzzle];
+                let chance = if laby_data.is_trap_inspected {
+                    trap.inspected_chance
+                } else {
+                    trap.disarm_chance
+                };
+                let outcome = if laby_rng.rng.gen_bool(chance.clamp(0.0, 1.0)) {
+                    &trap.success
+                } else {
+                    &trap.failure
+                };
+                let msg = end_puzzle(
+                    &mut laby_data,
+                    &laby_res,
+                    &mut player,
+                    &mut laby_rng,
+                    outcome,
+                );
+                console_writer.send(PrintConsoleEvent(msg));
+            }
+            "open" | "force" => {
+                if laby_data.room_type != RoomType::Gate {
+                    console_writer.send(PrintConsoleEvent("There is no gate here...".to_string()));
+                    continue;
+                }
+
+                let gate = &laby_res.gates[laby_data.puzzle];
+                let is_opened = if args[0] == "open" {
+                    if player.take_item(ItemType::Key).is_none() {
+                        console_writer.send(PrintConsoleEvent(
+                            "You need a key to open this gate... or 'force' it".to_string(),
+                        ));
+                        continue;
                     }
+                    true
+                } else {
+                    laby_rng.rng.gen_bool(gate.force_chance.clamp(0.0, 1.0))
+                };
 
+                if is_opened {
+                    let position = laby_data.position;
+                    laby_data.closed_gates.retain(|(room, _)| *room != position);
+                    let msg = end_puzzle(
+                        &mut laby_data,
+                        &laby_res,
+                        &mut player,
+                        &mut laby_rng,
+                        &gate.success,
+                    );
+                    console_writer.send(PrintConsoleEvent(msg));
+                } else {
+                    // the gate holds, the player can try again
+                    let msg = gate.failure.apply(&mut player, &laby_res.items).join("\n");
+                    console_writer.send(PrintConsoleEvent(msg.clone()));
+                    laby_data.status_message = msg;
                     laby_data.has_shown_turn_infos = false;
                     laby_data.wait_for_continue = false;
+                }
+            }
+            "loot" => {
+                if laby_data.room_type == RoomType::Item {
+                    match loot_room(&mut laby_data, &laby_res, &mut player, &mut laby_rng) {
+                        Ok(msg) => {
+                            laby_data.has_shown_turn_infos = false;
+                            laby_data.wait_for_continue = false;
 
-                    console_writer.send(PrintConsoleEvent(msg.clone()));
-                    laby_data.status_message = msg.clone();
-                    new_turn(&mut laby_data, &laby_res, &mut player, &npc_res);
+                            console_writer.send(PrintConsoleEvent(msg.clone()));
+                            laby_data.status_message = msg.clone();
+                            clear_room(&mut laby_data, &laby_res, &mut laby_rng);
+                        }
+                        Err(msg) => console_writer.send(PrintConsoleEvent(msg)),
+                    }
                 } else {
                     console_writer.send(PrintConsoleEvent(
                         "You try to loot this room..\n There is nothing but rocks (sry)..."
@@ -282,6 +860,131 @@ pub fn commands_handler(
                     ));
                 }
             }
+            "buy" | "sell" => {
+                if laby_data.room_type != RoomType::Merchant {
+                    console_writer.send(PrintConsoleEvent(
+                        "There is nobody to trade with here...".to_string(),
+                    ));
+                    continue;
+                }
+
+                let query = args[1..].join(" ");
+                if query.is_empty() {
+                    console_writer.send(PrintConsoleEvent(format!(
+                        "Usage: {} <item number or name>",
+                        args[0]
+                    )));
+                    continue;
+                }
+
+                let result = if args[0] == "buy" {
+                    buy_item(&mut laby_data, &mut player, &query)
+                } else {
+                    sell_item(&mut player, &query)
+                };
+
+                match result {
+                    Ok(msg) => {
+                        console_writer.send(PrintConsoleEvent(msg.clone()));
+                        laby_data.status_message = msg;
+                        laby_data.has_shown_turn_infos = false;
+                        laby_data.wait_for_continue = false;
+                    }
+                    Err(msg) => console_writer.send(PrintConsoleEvent(msg)),
+                }
+            }
+            "inventory" => {
+                console_writer.send(PrintConsoleEvent(display_inventory(&player)));
+            }
+            "equip" | "use" | "drop" => {
+                let query = args[1..].join(" ");
+                if query.is_empty() {
+                    console_writer.send(PrintConsoleEvent(format!(
+                        "Usage: {} <item number or name>, type 'inventory' to see your items",
+                        args[0]
+                    )));
+                    continue;
+                }
+
+                let index = match player.find_item(&query) {
+                    Some(index) => index,
+                    None => {
+                        console_writer.send(PrintConsoleEvent(
+                            "There is no such item in your inventory...".to_string(),
+                        ));
+                        continue;
+                    }
+                };
+                let kind = player.inventory[index].kind;
+                // swapping, dropping or drinking takes a turn during a fight
+                let takes_turn = matches!(
+                    (args[0], kind),
+                    ("drop", _) | ("equip" | "use", ItemType::Sword) | ("use", ItemType::Potion)
+                );
+
+                let msg = match (args[0], kind) {
+                    ("drop", _) => {
+                        let item = player.inventory.remove(index);
+                        format!("You drop the {}", item.name)
+                    }
+                    ("equip", ItemType::Sword) | ("use", ItemType::Sword) => {
+                        equip_weapon(&mut player, index)
+                    }
+                    ("use", ItemType::Potion) => {
+                        let potion = player.inventory.remove(index);
+                        player.health = (player.health + potion.heal).min(player.max_health);
+
+                        let mut msg = format!("You drink the {}", potion.name);
+                        if potion.heal > 0.0 {
+                            msg.push_str(&format!(" and heal {} HP", potion.heal));
+                        }
+                        for kind in potion.cures.iter() {
+                            if player.effects.cure(*kind) {
+                                msg.push_str(&format!(
+                                    "\nYou are no longer {}",
+                                    kind.to_display_str().to_lowercase()
+                                ));
+                            }
+                        }
+                        if let Some(effect) = potion.effect {
+                            player.effects.apply(effect);
+                            msg.push_str(&format!(
+                                "\nYou are {}!",
+                                effect.kind.to_display_str().to_lowercase()
+                            ));
+                        }
+                        msg
+                    }
+                    ("use", ItemType::Key)
+                        if laby_data.room_type == RoomType::Item
+                            && laby_data.item_type == ItemType::Chest
+                            && laby_data.is_chest_locked =>
+                    {
+                        match loot_room(&mut laby_data, &laby_res, &mut player, &mut laby_rng) {
+                            Ok(msg) => {
+                                clear_room(&mut laby_data, &laby_res, &mut laby_rng);
+                                msg
+                            }
+                            Err(msg) => msg,
+                        }
+                    }
+                    ("use", ItemType::Key) => {
+                        "Keys open the locked chests and the locked doors you walk through"
+                            .to_string()
+                    }
+                    ("equip", _) => "You can only equip a sword...".to_string(),
+                    _ => "Nothing happens...".to_string(),
+                };
+
+                console_writer.send(PrintConsoleEvent(msg.clone()));
+                laby_data.status_message = msg;
+                laby_data.has_shown_turn_infos = false;
+                laby_data.wait_for_continue = false;
+
+                if takes_turn && laby_data.room_type == RoomType::Enemy {
+                    ai_turn(&mut player, &mut laby_data, &mut laby_rng);
+                }
+            }
 
             _ => {
                 console_writer.send(PrintConsoleEvent(format!(
@@ -293,21 +996,692 @@ pub fn commands_handler(
     }
 }
 
-fn ai_turn(player: &mut ResMut<PlayerStats>, laby_data: &mut ResMut<LabyrinthData>) {
+// answers the NPC, the conversation goes on or ends with the room
+fn choose_answer(
+    laby_data: &mut ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    player: &mut ResMut<PlayerStats>,
+    vuln_res: &mut ResMut<VulnerabilityResource>,
+    laby_rng: &mut ResMut<LabyrinthRng>,
+    number: &str,
+) -> Result<String, String> {
+    let state = laby_data
+        .dialogue
+        .ok_or_else(|| "Nobody is talking to you...".to_string())?;
+    let dialogue = &laby_res.dialogues[state.dialogue];
+    let choice = number
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_sub(1))
+        .and_then(|index| dialogue.nodes[state.node].choices.get(index))
+        .ok_or_else(|| "Pick one of the answers by its number...".to_string())?;
+
+    if let Some(condition) = &choice.condition {
+        if !condition.is_met(player) {
+            return Err(format!("You can't say that ({})", condition.to_display()));
+        }
+    }
+
+    let username = laby_data.npc.username.clone();
+    let mut messages = vec![format!("You: \"{}\"", choice.get_text(&username))];
+    let next = choice.next.as_ref().and_then(|id| dialogue.find_node(id));
+
+    // a node without choices is the last line of the conversation
+    let is_over = match next {
+        Some(index) => {
+            let node = &dialogue.nodes[index];
+            if node.choices.is_empty() {
+                messages.push(format!("{}: \"{}\"", username, node.get_line(&username)));
+            }
+            node.choices.is_empty()
+        }
+        None => true,
+    };
+
+    for effect in choice.effects.iter() {
+        let inventory_size = player.inventory.len();
+        messages.push(apply_dialogue_effect(
+            effect, laby_res, player, vuln_res, &username,
+        ));
+        match effect {
+            DialogueEffect::Enemy => {
+                laby_data.journal.on_npc(&username, "became your enemy");
+                laby_data.run_stats.on_anger(&username);
+            }
+            DialogueEffect::Friend => {
+                laby_data.journal.on_npc(&username, "became your friend");
+                laby_data.run_stats.on_befriend(&username);
+            }
+            DialogueEffect::GiveItem(name) if player.inventory.len() > inventory_size => {
+                laby_data.run_stats.items_found.push(name.clone());
+            }
+            _ => (),
+        }
+    }
+
+    if is_over {
+        clear_room(laby_data, laby_res, laby_rng);
+    } else if let Some(index) = next {
+        laby_data.dialogue = Some(DialogueState {
+            node: index,
+            ..state
+        });
+    }
+
+    Ok(messages.join("\n"))
+}
+
+fn apply_dialogue_effect(
+    effect: &DialogueEffect,
+    laby_res: &Res<LabyrinthResourceFile>,
+    player: &mut ResMut<PlayerStats>,
+    vuln_res: &mut ResMut<VulnerabilityResource>,
+    username: &str,
+) -> String {
+    match effect {
+        DialogueEffect::Boon { health, damages } => {
+            player.health += health;
+            player.damages += damages;
+            format!(
+                "{} gives you strength (+{} HP, +{} dmg)",
+                username, health, damages
+            )
+        }
+        DialogueEffect::GiveItem(name) => match Item::find_by_name(&laby_res.items, name) {
+            Some(item) => match player.add_item(item.clone()) {
+                Ok(()) => format!("{} gives you a {}", username, item.name),
+                Err(msg) => msg,
+            },
+            None => {
+                warn!("Unknown item in a dialogue: {}", name);
+                format!("{} searches their pockets but finds nothing", username)
+            }
+        },
+        DialogueEffect::GiveGold(gold) => {
+            player.gold += gold;
+            format!("{} gives you {} gold", username, gold)
+        }
+        DialogueEffect::TakeGold(gold) => {
+            player.gold = player.gold.saturating_sub(*gold);
+            format!("You give {} gold to {}", gold, username)
+        }
+        DialogueEffect::Effect(effect) => {
+            player.effects.apply(*effect);
+            format!("You are {}!", effect.kind.to_display_str().to_lowercase())
+        }
+        DialogueEffect::Relationship(amount) => {
+            vuln_res.relationships.change(username, *amount);
+            format!(
+                "{}: {}",
+                username,
+                vuln_res.relationships.to_display(username)
+            )
+        }
+        DialogueEffect::Enemy => {
+            vuln_res.relationships.make_hostile(username);
+            format!("{} will remember this...", username)
+        }
+        DialogueEffect::Friend => {
+            vuln_res.relationships.make_friend(username);
+            format!("I think you just made a friend! {} likes you", username)
+        }
+    }
+}
+
+// the party didn't like what they just saw, the ones who had enough leave
+fn disappoint_party(
+    player: &mut ResMut<PlayerStats>,
+    vuln_res: &mut ResMut<VulnerabilityResource>,
+) -> Vec<String> {
+    let mut res = Vec::new();
+
+    for companion in player.party.iter() {
+        let score = vuln_res
+            .relationships
+            .interact(&companion.username, Interaction::Disappoint);
+        if score <= LEAVE_THRESHOLD {
+            res.push(format!(
+                "{} had enough of you and leaves your party",
+                companion.username
+            ));
+        } else {
+            res.push(format!("{} looks disappointed...", companion.username));
+        }
+    }
+    player
+        .party
+        .retain(|companion| vuln_res.relationships.get(&companion.username) > LEAVE_THRESHOLD);
+
+    res
+}
+
+// how the NPC of the room takes what the player did
+fn interact_with_npc(
+    laby_data: &mut ResMut<LabyrinthData>,
+    vuln_res: &mut ResMut<VulnerabilityResource>,
+    interaction: Interaction,
+) -> String {
+    let username = laby_data.npc.username.clone();
+    laby_data.journal.on_interaction(&username, interaction);
+    let was_hostile = vuln_res.relationships.is_hostile(&username);
+    vuln_res.relationships.interact(&username, interaction);
+
+    match (was_hostile, vuln_res.relationships.is_hostile(&username)) {
+        (false, true) => {
+            laby_data.run_stats.on_anger(&username);
+            format!("{} will remember this...", username)
+        }
+        (true, false) => {
+            laby_data.run_stats.on_befriend(&username);
+            format!("{} doesn't hold a grudge anymore", username)
+        }
+        _ => format!(
+            "{}: {}",
+            username,
+            vuln_res.relationships.to_display(&username)
+        ),
+    }
+}
+
+// applies the outcome of a riddle, trap or gate, then the way is free
+fn end_puzzle(
+    laby_data: &mut ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    player: &mut ResMut<PlayerStats>,
+    laby_rng: &mut ResMut<LabyrinthRng>,
+    outcome: &RoomOutcome,
+) -> String {
+    let health = player.health;
+    let inventory_size = player.inventory.len();
+    let msg = outcome.apply(player, &laby_res.items).join("\n");
+    if let Some(item) = outcome.item.as_ref() {
+        if player.inventory.len() > inventory_size {
+            laby_data.run_stats.items_found.push(item.clone());
+        }
+    }
+    laby_data.run_stats.damages_taken += outcome.damages.min(health);
+    laby_data.add_log(&outcome.message);
+    laby_data.status_message = msg.clone();
+    clear_room(laby_data, laby_res, laby_rng);
     laby_data.has_shown_turn_infos = false;
     laby_data.wait_for_continue = false;
-    let damages = {
-        if player.last_action == PlayerActions::Protect {
-            laby_data.enemy.damages * 0.5
-        } else {
-            laby_data.enemy.damages
+
+    msg
+}
+
+// picks up the item of the room, chests hold a sword or a potion
+fn loot_room(
+    laby_data: &mut ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    player: &mut ResMut<PlayerStats>,
+    laby_rng: &mut ResMut<LabyrinthRng>,
+) -> Result<String, String> {
+    let is_locked = laby_data.item_type == ItemType::Chest && laby_data.is_chest_locked;
+    if is_locked && player.count_items(ItemType::Key) == 0 {
+        return Err("The chest is locked... You need a key to open it".to_string());
+    }
+
+    let kind = match laby_data.item_type {
+        ItemType::Chest => *[ItemType::Sword, ItemType::Potion]
+            .choose(&mut laby_rng.rng)
+            .unwrap(),
+        kind => kind,
+    };
+    let item = match Item::get_random_of_kind(&laby_res.items, kind, &mut laby_rng.rng) {
+        Some(item) => item.clone(),
+        None => return Ok("Ah crap its too rusty... maybe next time?".to_string()),
+    };
+
+    let mut msg = format!("You put the {} in your inventory", item.to_display());
+    let name = item.name.clone();
+    player.add_item(item)?;
+    laby_data.run_stats.items_found.push(name);
+
+    if is_locked {
+        player.take_item(ItemType::Key);
+        msg = format!("You open the chest with your key...\n{}", msg);
+    }
+    if laby_data.item_type == ItemType::Chest {
+        let gold = laby_rng.rng.gen_range(CHEST_GOLD);
+        player.gold += gold;
+        msg.push_str(&format!("\nThere were also {} gold in the chest!", gold));
+    }
+
+    Ok(msg)
+}
+
+fn buy_item(
+    laby_data: &mut ResMut<LabyrinthData>,
+    player: &mut ResMut<PlayerStats>,
+    query: &str,
+) -> Result<String, String> {
+    let index = match query.parse::<usize>() {
+        Ok(number) => number
+            .checked_sub(1)
+            .filter(|index| *index < laby_data.merchant_stock.len()),
+        Err(_) => laby_data
+            .merchant_stock
+            .iter()
+            .position(|(item, _)| item.name.to_lowercase() == query.to_lowercase()),
+    }
+    .ok_or_else(|| "\"I don't sell that, stranger\"".to_string())?;
+
+    let item = laby_data.merchant_stock[index].0.clone();
+    if player.gold < item.price {
+        return Err(format!(
+            "\"{} gold, no less!\" You only have {} gold...",
+            item.price, player.gold
+        ));
+    }
+
+    let msg = format!("You buy the {} for {} gold", item.name, item.price);
+    let price = item.price;
+    // the gold is only taken once the item fits in the bag
+    player.add_item(item)?;
+    player.gold -= price;
+
+    laby_data.merchant_stock[index].1 -= 1;
+    if laby_data.merchant_stock[index].1 == 0 {
+        laby_data.merchant_stock.remove(index);
+    }
+
+    Ok(msg)
+}
+
+fn sell_item(player: &mut ResMut<PlayerStats>, query: &str) -> Result<String, String> {
+    let index = player
+        .find_item(query)
+        .ok_or_else(|| "There is no such item in your inventory...".to_string())?;
+
+    let item = player.inventory.remove(index);
+    player.gold += item.get_sell_price();
+
+    Ok(format!(
+        "You sell the {} for {} gold",
+        item.name,
+        item.get_sell_price()
+    ))
+}
+
+// the previous weapon goes back in the inventory
+fn equip_weapon(player: &mut ResMut<PlayerStats>, index: usize) -> String {
+    let weapon = player.inventory.remove(index);
+    let msg = format!("You equip the {}", weapon.to_display());
+
+    if let Some(previous) = player.weapon.replace(weapon) {
+        player.inventory.insert(index, previous);
+    }
+
+    msg
+}
+
+fn display_inventory(player: &ResMut<PlayerStats>) -> String {
+    let mut res = String::from("-------------------[Inventory]--------------------\n");
+    res.push_str(&format!(
+        "Equipped: {}\n\n",
+        match &player.weapon {
+            Some(weapon) => weapon.to_display(),
+            None => "nothing".to_string(),
         }
+    ));
+
+    if player.inventory.is_empty() {
+        res.push_str("Your bag is empty...\n");
+    }
+    for (index, item) in player.inventory.iter().enumerate() {
+        res.push_str(&format!("{}. {}\n", index + 1, item.to_display()));
+    }
+    res.push_str(&format!(
+        "\n({}/{} items)\n",
+        player.inventory.len(),
+        INVENTORY_SIZE
+    ));
+
+    res
+}
+
+// raises a stat of the player for a skill point
+fn spend_point(
+    player: &mut ResMut<PlayerStats>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    name: &str,
+) -> Result<String, String> {
+    if player.skill_points == 0 {
+        return Err("You have no skill point left...".to_string());
+    }
+    let upgrade = laby_res
+        .upgrades
+        .iter()
+        .find(|upgrade| upgrade.name.to_lowercase() == name.to_lowercase())
+        .ok_or_else(|| "There is no such stat...".to_string())?;
+
+    player.skill_points -= 1;
+    player.max_health += upgrade.bonus.max_health;
+    player.health = (player.health + upgrade.bonus.health).min(player.max_health);
+    player.damages += upgrade.bonus.damages;
+
+    Ok(format!(
+        "Your {} improves! ({} points left)",
+        upgrade.name, player.skill_points
+    ))
+}
+
+// buys a perk with skill points, once the level is high enough
+fn learn_skill(
+    player: &mut ResMut<PlayerStats>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    name: &str,
+) -> Result<String, String> {
+    let skill = laby_res
+        .skills
+        .iter()
+        .find(|skill| skill.name.to_lowercase() == name.to_lowercase())
+        .ok_or_else(|| "There is no such skill...".to_string())?;
+
+    if player.has_skill(&skill.name) {
+        return Err(format!("You already know {}", skill.name));
+    }
+    if player.level < skill.min_level {
+        return Err(format!("You need to be level {} first", skill.min_level));
+    }
+    if player.skill_points < skill.cost {
+        return Err(format!("You need {} skill points to learn it", skill.cost));
+    }
+
+    player.skill_points -= skill.cost;
+    player.skills.push(skill.clone());
+
+    Ok(format!(
+        "You learned {}! ({} points left)",
+        skill.name, player.skill_points
+    ))
+}
+
+// the signature move of the class of the player
+fn use_ability(player: &mut ResMut<PlayerStats>, laby_data: &mut ResMut<LabyrinthData>) -> String {
+    let ability = match &player.class {
+        Some(class) => class.ability.clone(),
+        None => return "You don't know any special move...".to_string(),
     };
-    laby_data
-        .status_message
-        .push_str(&format!("\nThe enemy attacks you for {} HP.", damages,));
-    player.health -= damages;
+    // the turn it's used doesn't count
+    let focus = player.get_skill_bonus(SkillKind::Focus) as usize;
+    player.ability_cooldown = ability.cooldown.saturating_sub(focus) + 1;
+
+    let mut res = format!("You use {}!", ability.name);
+    if ability.damages > 0.0 {
+        let mut damages = player.get_damages() * ability.damages;
+        if player.last_action == PlayerActions::Prepare {
+            damages *= 2.5;
+        }
+        if !ability.piercing && laby_data.enemy.intention == EnemyAction::Defend {
+            damages *= 0.5;
+        }
+        if !ability.piercing && laby_data.enemy.effects.has(EffectType::Shield) {
+            damages = laby_data.enemy.effects.absorb(damages);
+        }
+        laby_data.enemy.health -= damages;
+        laby_data.run_stats.damages_dealt += damages;
+        res.push_str(&format!(
+            "\nThe {} takes {} damage",
+            laby_data.enemy.name, damages
+        ));
+    }
+    if ability.heal > 0.0 {
+        player.health = (player.health + ability.heal).min(player.max_health);
+        res.push_str(&format!("\nYou heal {} HP", ability.heal));
+    }
+    if let Some(effect) = ability.effect {
+        if laby_data.enemy.effects.apply(effect) {
+            res.push_str(&format!(
+                "\nThe {} is {}!",
+                laby_data.enemy.name,
+                effect.kind.to_display_str().to_lowercase()
+            ));
+        }
+    }
+    if let Some(effect) = ability.self_effect {
+        player.effects.apply(effect);
+        res.push_str(&format!(
+            "\nYou are {}!",
+            effect.kind.to_display_str().to_lowercase()
+        ));
+    }
+
+    res
+}
+
+// The enemy does what it announced, then picks its next move.
+// The effects of both sides tick once per turn.
+fn ai_turn(
+    player: &mut ResMut<PlayerStats>,
+    laby_data: &mut ResMut<LabyrinthData>,
+    laby_rng: &mut ResMut<LabyrinthRng>,
+) {
+    laby_data.has_shown_turn_infos = false;
+    laby_data.wait_for_continue = false;
+    player.ability_cooldown = player.ability_cooldown.saturating_sub(1);
+    let health = player.health;
+
+    // the effects of the player tick first, so that the ones the enemy
+    // gives this turn last for the whole next turn
+    let max_health = player.max_health;
+    let stats = &mut **player;
+    let mut messages = stats.effects.tick("You", &mut stats.health, max_health);
+
+    // the stun is checked before it wears off
+    let is_stunned = laby_data.enemy.effects.has(EffectType::Stun);
+    let enemy_health = laby_data.enemy.health;
+    {
+        let enemy = &mut laby_data.enemy;
+        let name = format!("The {}", enemy.name);
+        messages.extend(
+            enemy
+                .effects
+                .tick(&name, &mut enemy.health, enemy.max_health),
+        );
+    }
+
+    // the party strikes along with the player
+    for companion in player.party.iter() {
+        let enemy = &mut laby_data.enemy;
+        if enemy.health <= 0.0 {
+            break;
+        }
+        let damages = enemy.effects.absorb(companion.damages);
+        enemy.health -= damages;
+        messages.push(format!(
+            "{} hits the {} for {} damage",
+            companion.username, enemy.name, damages
+        ));
+    }
+    laby_data.run_stats.damages_dealt += (enemy_health - laby_data.enemy.health).max(0.0);
+
+    if let Some(msg) = laby_data.enemy.update_phase() {
+        messages.push(msg);
+    }
+
+    if laby_data.enemy.health > 0.0 {
+        let enemy = &mut laby_data.enemy;
+        let rng = laby_rng.for_enemy(enemy);
+        let target = match enemy.intention {
+            EnemyAction::Attack => Companion::choose_target(&player.party, rng),
+            _ => None,
+        };
+
+        if is_stunned {
+            messages.push(format!("The {} is stunned and can't move!", enemy.name));
+        } else if let Some(index) = target {
+            messages.push(attack_companion(&mut player.party[index], enemy));
+        } else {
+            messages.push(enemy_action(player, enemy));
+        }
+        enemy.choose_intention(rng);
+    }
+
     player.health = player.health.max(0.0);
+    laby_data.run_stats.damages_taken += (health - player.health).max(0.0);
+
+    // the knocked out companions crawl back to the entrance
+    for companion in player.party.iter().filter(|c| c.health <= 0.0) {
+        messages.push(format!(
+            "{} is knocked out and leaves your party...",
+            companion.username
+        ));
+    }
+    player.party.retain(|companion| companion.health > 0.0);
+
+    for msg in messages {
+        laby_data.status_message.push_str(&format!("\n{}", msg));
+    }
+}
+
+fn attack_companion(companion: &mut Companion, enemy: &mut Enemy) -> String {
+    let mut damages = enemy.damages;
+    if enemy.is_charged {
+        damages *= 2.0;
+        enemy.is_charged = false;
+    }
+    companion.health = (companion.health - damages).max(0.0);
+
+    format!(
+        "The {} attacks {} for {} HP.",
+        enemy.name, companion.username, damages
+    )
+}
+
+fn enemy_action(player: &mut PlayerStats, enemy: &mut Enemy) -> String {
+    let protection = if player.action == PlayerActions::Protect {
+        0.5
+    } else {
+        1.0
+    };
+    let toughness = player.get_skill_bonus(SkillKind::Toughness);
+
+    match (enemy.intention, enemy.special.clone()) {
+        (EnemyAction::Special, Some(special)) => {
+            let damages = player
+                .effects
+                .absorb((enemy.damages * special.damages * protection - toughness).max(0.0));
+            player.health -= damages;
+            enemy.health = (enemy.health + special.heal).min(enemy.max_health);
+
+            let mut msg = format!("The {} uses {}!", enemy.name, special.name);
+            if damages > 0.0 {
+                msg.push_str(&format!(" You lose {} HP.", damages));
+            }
+            if special.heal > 0.0 {
+                msg.push_str(&format!(" It heals {} HP.", special.heal));
+            }
+            if let Some(effect) = special.effect {
+                if player.effects.apply(effect) {
+                    msg.push_str(&format!(
+                        "\nYou are {}!",
+                        effect.kind.to_display_str().to_lowercase()
+                    ));
+                }
+            }
+            if let Some(effect) = special.self_effect {
+                enemy.effects.apply(effect);
+                msg.push_str(&format!(
+                    "\nThe {} is {}!",
+                    enemy.name,
+                    effect.kind.to_display_str().to_lowercase()
+                ));
+            }
+            msg
+        }
+        (EnemyAction::Charge, _) => {
+            enemy.is_charged = true;
+            format!("The {} charges up...", enemy.name)
+        }
+        (EnemyAction::Defend, _) => format!("The {} keeps its guard up.", enemy.name),
+        (EnemyAction::Flee, _) => {
+            enemy.has_fled = true;
+            format!("The {} runs away!", enemy.name)
+        }
+        _ => {
+            let mut damages = enemy.damages * protection;
+            if enemy.is_charged {
+                damages *= 2.0;
+                enemy.is_charged = false;
+            }
+            damages = player.effects.absorb((damages - toughness).max(0.0));
+            player.health -= damages;
+            format!("The {} attacks you for {} HP.", enemy.name, damages)
+        }
+    }
+}
+
+fn display_journal(
+    laby_data: &ResMut<LabyrinthData>,
+    vuln_res: &ResMut<VulnerabilityResource>,
+    page: &str,
+) -> Option<String> {
+    let journal = &laby_data.journal;
+    let mut res = String::from("--------------------[Journal]---------------------\n");
+
+    match page {
+        "bestiary" => {
+            if journal.bestiary.is_empty() {
+                res.push_str("\nYou haven't met any creature yet...\n");
+            }
+            for entry in journal.bestiary.iter() {
+                res.push_str(&entry.art);
+                res.push('\n');
+                res.push_str(&format!(
+                    "{} - met {} times, killed {}\n",
+                    entry.name, entry.encounters, entry.kills
+                ));
+                res.push_str(&format!("{}\n\n", entry.description.trim()));
+            }
+        }
+        "npcs" => {
+            if journal.npcs.is_empty() {
+                res.push_str("\nYou haven't met anyone yet...\n");
+            }
+            for entry in journal.npcs.iter() {
+                res.push_str(&format!(
+                    "\n{} - {}\n",
+                    entry.username,
+                    vuln_res.relationships.to_display(&entry.username)
+                ));
+                for event in entry.events.iter() {
+                    res.push_str(&format!("  - {}\n", event));
+                }
+            }
+        }
+        "log" => {
+            res.push('\n');
+            for event in journal.log.iter() {
+                res.push_str(&format!("{}\n", event));
+            }
+        }
+        _ => return None,
+    }
+    res.push_str("\n(journal bestiary | journal npcs | journal log)\n");
+
+    Some(res)
+}
+
+fn display_quests(laby_data: &ResMut<LabyrinthData>) -> String {
+    let mut res = String::from("---------------------[Quests]---------------------\n");
+
+    if laby_data.quests.is_empty() {
+        res.push_str("Nobody asked you anything yet...\n");
+    }
+    for quest in laby_data.quests.iter() {
+        res.push_str(&format!("- {}\n", quest.to_display(laby_data.position)));
+    }
+
+    res
+}
+
+fn display_map(laby_data: &ResMut<LabyrinthData>) -> String {
+    let mut res = String::from("-----------------------[Map]----------------------\n");
+    res.push_str(&laby_data.maze.display(laby_data.position, laby_data.facing));
+    res.push_str("You: ^ > v < | Start: S | Boss: B | Explored: . | Locked: #\n");
+
+    res
 }
 
 fn display_help(page_number: usize) -> String {
@@ -321,20 +1695,52 @@ fn display_help(page_number: usize) -> String {
         res.push_str("- clear: Clears commands on the screen\n");
         res.push_str("- tutorial: Show the tutorial for this game\n");
         res.push_str("- go <direction>: Move the player to the next direction\n");
+        res.push_str("- descend: Takes the stairs to the next floor\n");
+        res.push_str("- map: Shows the part of the maze you explored\n");
         res.push_str("- ragequit: Leaves the game (you will lose your progress)\n");
         res.push_str("- infos: Display informations about the place you stand\n");
         res.push_str("- skip: skip this room to go to the next (if you can)\n");
-    } else {
-        res.push_str("- talk: talks to an npc to maybe receive a boon\n");
+        res.push_str("- choose <class>: Picks your class before entering the labyrinth\n");
+        res.push_str("- levelup: Shows how to spend your skill points\n");
+        res.push_str("- spend <stat>: Raises a stat for a skill point\n");
+        res.push_str("- learn <skill>: Learns a skill with your skill points\n");
+    } else if page_number == 2 {
+        res.push_str("- talk: starts a conversation with an npc\n");
+        res.push_str("- say <number>: answers the npc (or just type the number)\n");
         res.push_str("- insult: insults the npc to become stronger\n");
+        res.push_str("- give <item>: gives an item to the npc to be friends\n");
+        res.push_str("- apologize: apologizes to an npc you upset\n");
+        res.push_str("- invite: asks a friendly npc to join your party\n");
+        res.push_str("- dismiss <username>: sends a companion away\n");
+        res.push_str("- accept: accepts the quest of an npc\n");
+        res.push_str("- quests: lists the quests you accepted\n");
+        res.push_str("- journal <bestiary|npcs|log>: what you saw during this run\n");
+        res.push_str("- history [endless|speedrun]: past runs and leaderboards\n");
         res.push_str("- loot: loots the item (when you find one)\n");
         res.push_str("- continue: to continue a story/speech\n");
         res.push_str("- attack: attacks the monster / NPC\n");
         res.push_str("- prepare: prepares the attack for x2.5 damages\n");
         res.push_str("- protect: a protection position to take x0.5 damages\n");
+        res.push_str("- <ability>: uses the signature move of your class\n");
+        res.push_str("- answer <words>: answers the riddle of a door\n");
+        res.push_str("- inspect: takes a closer look at a trap\n");
+        res.push_str("- disarm: tries to disarm a trap\n");
+        res.push_str("- open: unlocks a gate with a key\n");
+        res.push_str("- force: tries to break a gate open\n");
+    } else {
+        res.push_str("- inventory: Lists the items you carry\n");
+        res.push_str("- equip <item>: Equips a sword from your inventory\n");
+        res.push_str("- use <item>: Drinks a potion, equips a sword or opens a chest\n");
+        res.push_str("- drop <item>: Throws an item away\n");
+        res.push_str("- buy <item>: Buys an item to a merchant\n");
+        res.push_str("- sell <item>: Sells an item to a merchant for half its price\n");
+        res.push_str("  (<item> is its number in the inventory or its name)\n");
     }
 
-    res.push_str(&format!("\n============({}/2)===========\n", page_number));
+    res.push_str(&format!(
+        "\n============({}/{})===========\n",
+        page_number, HELP_PAGES
+    ));
 
     res
 }
@@ -0,0 +1,85 @@
+use rand::{prelude::SliceRandom, Rng};
+use serde::Deserialize;
+
+use super::data::LevelBonus;
+
+// a companion whose relationship with the player falls to it leaves the party
+pub const LEAVE_THRESHOLD: i32 = 0;
+
+/// Stats of the NPCs who join the player
+#[derive(Debug, Deserialize)]
+pub struct CompanionRules {
+    pub party_size: usize,
+    // stats at level 1
+    pub max_health: f32,
+    pub damages: f32,
+    pub level_bonus: LevelBonus,
+    // exp needed for each level
+    pub exp_per_level: usize,
+}
+
+/// A friendly NPC fighting alongside the player
+#[derive(Debug, Clone)]
+pub struct Companion {
+    pub username: String,
+    pub health: f32,
+    pub max_health: f32,
+    pub level: usize,
+    pub exp: usize,
+    pub damages: f32,
+}
+
+impl Companion {
+    /// The NPC joins at the level of the player
+    pub fn new(username: &str, level: usize, rules: &CompanionRules) -> Companion {
+        let bonus = (level - 1) as f32;
+        let max_health = rules.max_health + rules.level_bonus.max_health * bonus;
+
+        Companion {
+            username: username.to_string(),
+            health: max_health,
+            max_health,
+            level,
+            exp: 0,
+            damages: rules.damages + rules.level_bonus.damages * bonus,
+        }
+    }
+
+    /// Returns true when the companion levels up
+    pub fn gain_exp(&mut self, exp: usize, rules: &CompanionRules) -> bool {
+        self.exp += exp;
+        if self.exp < rules.exp_per_level {
+            return false;
+        }
+
+        self.exp -= rules.exp_per_level;
+        self.level += 1;
+        self.max_health += rules.level_bonus.max_health;
+        self.health = (self.health + rules.level_bonus.health).min(self.max_health);
+        self.damages += rules.level_bonus.damages;
+        true
+    }
+
+    pub fn heal(&mut self, health: f32) {
+        self.health = (self.health + health).min(self.max_health);
+    }
+
+    pub fn to_display(&self) -> String {
+        format!(
+            "{} (Lv {}, {}/{} HP)",
+            self.username, self.level, self.health, self.max_health
+        )
+    }
+
+    /// Who the enemy goes after: the player (None) or one of the party,
+    /// the player is twice as likely to be targeted as each companion
+    pub fn choose_target(party: &[Companion], rng: &mut impl Rng) -> Option<usize> {
+        let mut targets: Vec<(Option<usize>, u32)> = vec![(None, 2)];
+        targets.extend((0..party.len()).map(|index| (Some(index), 1)));
+
+        targets
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .map(|(target, _)| *target)
+            .unwrap_or(None)
+    }
+}
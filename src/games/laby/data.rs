@@ -1,15 +1,39 @@
-use rand::Rng;
-use ron::de::from_bytes;
+use std::{
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::Timer;
+use rand::{rngs::StdRng, SeedableRng};
 use serde::Deserialize;
 
 use crate::npcs::NPCData;
 
-use super::{art, enemies::Enemy, items::ItemType};
+use super::{
+    art,
+    classes::PlayerClass,
+    companions::{Companion, CompanionRules},
+    dialogues::{Dialogue, DialogueState},
+    effects::StatusEffects,
+    enemies::{Enemy, RivalRules},
+    floors::Floor,
+    items::{Item, ItemType, MerchantStock, INVENTORY_SIZE},
+    journal::Journal,
+    maze::{Cardinal, Maze},
+    puzzles::{Gate, Riddle, Trap},
+    quests::{Quest, QuestTemplate},
+    skills::{ExpCurve, Skill, SkillKind, StatUpgrade},
+    summary::Split,
+};
 
 #[derive(PartialEq)]
 pub enum GameState {
     Tutorial,
+    // choosing a class before the first room
+    CharacterCreation,
     Exploring,
+    // spending the skill points of the new levels
+    LevelUp,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -25,9 +49,20 @@ pub struct PlayerStats {
     pub max_health: f32,
     pub level: usize,
     pub exp: usize,
+    pub gold: usize,
     pub damages: f32,
     pub action: PlayerActions,
     pub last_action: PlayerActions,
+    pub inventory: Vec<Item>,
+    pub weapon: Option<Item>,
+    pub effects: StatusEffects,
+    // the friendly NPCs following the player
+    pub party: Vec<Companion>,
+    pub class: Option<PlayerClass>,
+    // fight turns before the ability of the class is ready
+    pub ability_cooldown: usize,
+    pub skill_points: usize,
+    pub skills: Vec<Skill>,
 }
 
 impl Default for PlayerStats {
@@ -37,9 +72,18 @@ impl Default for PlayerStats {
             max_health: 10.0,
             level: 1,
             exp: 0,
+            gold: 0,
             damages: 1.0,
             action: PlayerActions::Attack,
             last_action: PlayerActions::Attack,
+            inventory: Vec::new(),
+            weapon: None,
+            effects: StatusEffects::default(),
+            party: Vec::new(),
+            class: None,
+            ability_cooldown: 0,
+            skill_points: 0,
+            skills: Vec::new(),
         }
     }
 }
@@ -47,50 +91,238 @@ impl Default for PlayerStats {
 impl PlayerStats {
     pub fn reset(&mut self) {
         self.exp = 0;
+        self.gold = 0;
         self.level = 1;
         self.damages = 1.0;
         self.max_health = 10.0;
         self.health = self.max_health;
         self.action = PlayerActions::Attack;
+        self.inventory.clear();
+        self.weapon = None;
+        self.effects.clear();
+        self.party.clear();
+        self.class = None;
+        self.ability_cooldown = 0;
+        self.skill_points = 0;
+        self.skills.clear();
+    }
+
+    /// What all the learned skills of this kind give
+    pub fn get_skill_bonus(&self, kind: SkillKind) -> f32 {
+        self.skills
+            .iter()
+            .filter(|skill| skill.kind == kind)
+            .map(|skill| skill.power)
+            .sum()
+    }
+
+    pub fn has_skill(&self, name: &str) -> bool {
+        self.skills.iter().any(|skill| skill.name == name)
+    }
+
+    /// Gives the stats and the starting items of the class
+    pub fn choose_class(&mut self, class: &PlayerClass, items: &[Item]) {
+        self.max_health = class.max_health;
+        self.health = self.max_health;
+        self.damages = class.damages;
+        self.gold += class.gold;
+        for name in class.items.iter() {
+            if let Some(item) = Item::find_by_name(items, name) {
+                // the inventory is empty at this point
+                self.add_item(item.clone()).ok();
+            }
+        }
+        self.class = Some(class.clone());
+    }
+
+    // damages with the bonus of the equipped weapon
+    pub fn get_damages(&self) -> f32 {
+        match &self.weapon {
+            Some(weapon) => self.damages + weapon.damages,
+            None => self.damages,
+        }
+    }
+
+    pub fn add_item(&mut self, item: Item) -> Result<(), String> {
+        if self.inventory.len() >= INVENTORY_SIZE {
+            return Err("Your inventory is full... 'drop' something first".to_string());
+        }
+        self.inventory.push(item);
+        Ok(())
+    }
+
+    pub fn count_items(&self, kind: ItemType) -> usize {
+        self.inventory
+            .iter()
+            .filter(|item| item.kind == kind)
+            .count()
+    }
+
+    /// Removes an item of this kind from the inventory, if there is one
+    pub fn take_item(&mut self, kind: ItemType) -> Option<Item> {
+        let index = self.inventory.iter().position(|item| item.kind == kind)?;
+        Some(self.inventory.remove(index))
+    }
+
+    /// Finds an item by its number in the inventory or by its name
+    pub fn find_item(&self, query: &str) -> Option<usize> {
+        if let Ok(number) = query.parse::<usize>() {
+            return number
+                .checked_sub(1)
+                .filter(|index| *index < self.inventory.len());
+        }
+
+        let query = query.to_lowercase();
+        self.inventory.iter().position(|item| {
+            item.name.to_lowercase() == query || item.kind.to_display_str() == query
+        })
     }
 }
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
 pub enum RoomType {
     Corridor,
     Enemy,
     Item,
     Npc,
+    Merchant,
+    Riddle,
+    Trap,
+    Gate,
 }
 
 pub struct LabyrinthData {
     pub steps_number: usize,
     pub room_type: RoomType,
+    // the content of the last new room, to avoid twice the same in a row
+    pub last_rolled_room: RoomType,
     pub enemy: Enemy,
     pub npc: NPCData,
     pub seen_npcs: Vec<String>,
+    // the hostile NPCs already met during this run
+    pub met_rivals: Vec<String>,
+    // what the NPC of the room asks for
+    pub offered_quest: Option<Quest>,
+    pub quests: Vec<Quest>,
+    // the conversation going on with the NPC of the room
+    pub dialogue: Option<DialogueState>,
     pub item_type: ItemType,
+    // chests found behind a locked door are already open
+    pub is_chest_locked: bool,
+    // what the merchant of the room still has to sell
+    pub merchant_stock: Vec<(Item, usize)>,
+    // index of the riddle, trap or gate of the room
+    pub puzzle: usize,
+    pub is_trap_inspected: bool,
+    // the gates left closed behind the player, with their index
+    pub closed_gates: Vec<((i32, i32), usize)>,
     pub next_directions: Directions,
+    pub maze: Maze,
+    // the floors go from 0 to the boss
+    pub floor: usize,
+    // the guardian of the floor is beaten
+    pub is_stairs_open: bool,
+    // where the player stands in the maze and where they look at
+    pub position: (i32, i32),
+    pub facing: Cardinal,
+    // running while the player walks to the next room
+    pub walking: Option<Timer>,
+    // typed during the walk, played once in the next room
+    pub queued_commands: Vec<String>,
     pub has_shown_turn_infos: bool,
     pub wait_for_continue: bool,
     pub game_state: GameState,
     pub description: String,
     pub status_message: String,
     pub tutorial_page: usize,
+    pub mode: RunMode,
+    pub run_stats: RunStats,
+    pub journal: Journal,
+}
+
+/// What the run was like, shown once it's over
+#[derive(Debug, Default)]
+pub struct RunStats {
+    pub class: Option<String>,
+    // when the player entered the first room
+    pub started: Option<Instant>,
+    // the time of the run once it's over, the clock stops there
+    pub finished: Option<Duration>,
+    // minutes passed on the clock of the apartment
+    pub game_time: f32,
+    // the times at which each floor was left
+    pub splits: Vec<Split>,
+    // the deepest point reached in endless mode
+    pub depth_score: usize,
+    pub rooms_cleared: usize,
+    pub damages_dealt: f32,
+    pub damages_taken: f32,
+    pub items_found: Vec<String>,
+    pub befriended: Vec<String>,
+    pub angered: Vec<String>,
+}
+
+impl RunStats {
+    pub fn get_duration(&self) -> Duration {
+        match self.finished {
+            Some(duration) => duration,
+            None => self
+                .started
+                .map(|started| started.elapsed())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some() && self.finished.is_none()
+    }
+
+    pub fn stop_clock(&mut self) {
+        if self.is_running() {
+            self.finished = Some(self.get_duration());
+        }
+    }
+
+    pub fn on_befriend(&mut self, username: &str) {
+        self.angered.retain(|name| name != username);
+        if !self.befriended.iter().any(|name| name == username) {
+            self.befriended.push(username.to_string());
+        }
+    }
+
+    pub fn on_anger(&mut self, username: &str) {
+        self.befriended.retain(|name| name != username);
+        if !self.angered.iter().any(|name| name == username) {
+            self.angered.push(username.to_string());
+        }
+    }
 }
 
 impl Default for LabyrinthData {
     fn default() -> Self {
         LabyrinthData {
             next_directions: Directions::All,
+            maze: Maze::default(),
+            floor: 0,
+            is_stairs_open: false,
+            position: (0, 0),
+            facing: Cardinal::North,
             game_state: GameState::Tutorial,
+            walking: None,
+            queued_commands: Vec::new(),
             has_shown_turn_infos: false,
             steps_number: 0,
             room_type: RoomType::Corridor,
+            last_rolled_room: RoomType::Corridor,
             wait_for_continue: false,
             description: String::from(""),
             enemy: Enemy::default(),
             item_type: ItemType::Chest,
+            is_chest_locked: true,
+            merchant_stock: Vec::new(),
+            puzzle: 0,
+            is_trap_inspected: false,
+            closed_gates: Vec::new(),
             status_message: String::from(""),
             npc: NPCData {
                 sprite_id: 0,
@@ -98,6 +330,13 @@ impl Default for LabyrinthData {
             },
             tutorial_page: 0,
             seen_npcs: Vec::new(),
+            met_rivals: Vec::new(),
+            offered_quest: None,
+            quests: Vec::new(),
+            dialogue: None,
+            mode: RunMode::Story,
+            run_stats: RunStats::default(),
+            journal: Journal::default(),
         }
     }
 }
@@ -105,11 +344,204 @@ impl Default for LabyrinthData {
 impl LabyrinthData {
     pub fn reset(&mut self) {
         self.steps_number = 0;
+        self.floor = 0;
+        self.is_stairs_open = false;
+        self.room_type = RoomType::Corridor;
+        self.last_rolled_room = RoomType::Corridor;
         self.next_directions = Directions::All;
+        self.walking = None;
+        self.dialogue = None;
+        self.seen_npcs.clear();
+        self.met_rivals.clear();
+        self.offered_quest = None;
+        self.quests.clear();
+        self.closed_gates.clear();
+        self.queued_commands.clear();
         self.has_shown_turn_infos = false;
         self.wait_for_continue = false;
         self.game_state = GameState::Tutorial;
         self.tutorial_page = 0;
+        self.mode = RunMode::Story;
+        self.run_stats = RunStats::default();
+        self.journal.clear();
+    }
+
+    /// Writes a notable event in the journal
+    pub fn add_log(&mut self, event: &str) {
+        self.journal.add_log(self.floor, self.steps_number, event);
+    }
+}
+
+// keeps the stream of the rivals apart from the one of the run
+const RIVAL_STREAM: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Every roll of a run goes through this rng so that the same seed
+/// always gives the same labyrinth
+pub struct LabyrinthRng {
+    pub seed: u64,
+    pub rng: StdRng,
+    // the rivals depend on the relationships of the player, they
+    // get their own stream so that they don't shift the other rolls
+    pub rival_rng: StdRng,
+}
+
+impl Default for LabyrinthRng {
+    fn default() -> Self {
+        LabyrinthRng {
+            seed: 0,
+            rng: StdRng::seed_from_u64(0),
+            rival_rng: StdRng::seed_from_u64(RIVAL_STREAM),
+        }
+    }
+}
+
+impl LabyrinthRng {
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+        self.rival_rng = StdRng::seed_from_u64(seed ^ RIVAL_STREAM);
+    }
+
+    /// The rng of a fight, a rival fights with the stream of the rivals
+    pub fn for_enemy(&mut self, enemy: &Enemy) -> &mut StdRng {
+        match enemy.rival {
+            Some(_) => &mut self.rival_rng,
+            None => &mut self.rng,
+        }
+    }
+}
+
+// Options given to 'play labyrinth'
+pub struct RunOptions {
+    pub seed: Option<u64>,
+    pub mode: RunMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunMode {
+    // down the floors to the boss
+    Story,
+    // the floors never end and the enemies keep getting stronger
+    Endless,
+    // the story against the clock
+    Speedrun,
+}
+
+impl RunMode {
+    pub fn to_display_str(self) -> &'static str {
+        match self {
+            RunMode::Story => "Story",
+            RunMode::Endless => "Endless",
+            RunMode::Speedrun => "Speedrun",
+        }
+    }
+}
+
+impl RunOptions {
+    /// Reads the `--seed N`, `--endless` and `--speedrun` options
+    pub fn from_args(args: &[String]) -> Result<RunOptions, String> {
+        let mut options = RunOptions {
+            seed: None,
+            mode: RunMode::Story,
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| format!("Option '{}' expects a value", arg))?;
+                    let seed = value
+                        .parse::<u64>()
+                        .map_err(|_| format!("Option '{}' expects a positive number", arg))?;
+                    options.seed = Some(seed);
+                }
+                "--endless" | "--speedrun" => {
+                    if options.mode != RunMode::Story {
+                        return Err("Pick either '--endless' or '--speedrun'".to_string());
+                    }
+                    options.mode = if arg == "--endless" {
+                        RunMode::Endless
+                    } else {
+                        RunMode::Speedrun
+                    };
+                }
+                _ => return Err(format!("Unknown option '{}'", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+// Stats gained on each level
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LevelBonus {
+    pub max_health: f32,
+    pub health: f32,
+    pub damages: f32,
+}
+
+// a room takes 4 characters on the map, which has to fit in the console
+const MAZE_SIZE: RangeInclusive<i32> = 2..=12;
+
+/// The balance of a run, tweakable without recompiling
+#[derive(Debug, Deserialize)]
+pub struct LabyrinthRules {
+    // the size of the maze gives the length of a run
+    pub maze_width: i32,
+    pub maze_height: i32,
+    pub treasure_rooms: usize,
+    // how likely each room is to be rolled
+    pub room_weights: Vec<(RoomType, u32)>,
+    pub boss: Enemy,
+    pub exp_curve: ExpCurve,
+    // given on each new level
+    pub skill_points_per_level: usize,
+    pub player_level_bonus: LevelBonus,
+    // what the enemies gain for each level of the player
+    pub enemy_level_bonus: LevelBonus,
+    // and for each room between the entrance and them
+    pub enemy_depth_bonus: LevelBonus,
+    pub companion: CompanionRules,
+    // chance for an NPC to have a quest for the player
+    pub quest_chance: f64,
+    pub rival: RivalRules,
+    // in endless mode each floor counts as this many rooms of depth
+    pub endless_floor_depth: usize,
+}
+
+impl LabyrinthRules {
+    /// Checks the values a run can't be played with
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, size) in [
+            ("maze_width", self.maze_width),
+            ("maze_height", self.maze_height),
+        ] {
+            if !MAZE_SIZE.contains(&size) {
+                return Err(format!(
+                    "{} is {} (valid: {}-{})",
+                    name,
+                    size,
+                    MAZE_SIZE.start(),
+                    MAZE_SIZE.end()
+                ));
+            }
+        }
+        if self.room_weights.iter().all(|(_, weight)| *weight == 0) {
+            return Err("room_weights needs at least one room with a weight".to_string());
+        }
+        for (name, chance) in [
+            ("quest_chance", self.quest_chance),
+            ("rival.chance", self.rival.chance),
+        ] {
+            if !(0.0..=1.0).contains(&chance) {
+                return Err(format!("{} is {} (valid: 0.0-1.0)", name, chance));
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -119,19 +551,48 @@ pub struct LabyrinthResourceFile {
     pub descriptions: Vec<String>,
     pub tutorial: Vec<String>,
     pub enemies: Vec<Enemy>,
+    pub items: Vec<Item>,
+    pub merchant_stock: Vec<MerchantStock>,
+    pub dialogues: Vec<Dialogue>,
+    pub quests: Vec<QuestTemplate>,
+    pub riddles: Vec<Riddle>,
+    pub traps: Vec<Trap>,
+    pub gates: Vec<Gate>,
+    pub floors: Vec<Floor>,
+    pub classes: Vec<PlayerClass>,
+    pub upgrades: Vec<StatUpgrade>,
+    pub skills: Vec<Skill>,
+    pub rules: LabyrinthRules,
 }
 
 impl LabyrinthResourceFile {
-    pub fn reset(&mut self) {
-        let new_res =  from_bytes::<LabyrinthResourceFile>(
-            include_bytes!(
-                "../../../data/labyrinth_data.ron"
-            )
-        ).unwrap();
+    /// The boss waits after the last floor, there is none in endless mode
+    pub fn is_last_floor(&self, floor: usize, mode: RunMode) -> bool {
+        mode != RunMode::Endless && floor + 1 >= self.floors.len()
+    }
 
-        self.enemies = new_res.enemies;
-        self.descriptions = new_res.descriptions;
-        self.tutorial = new_res.tutorial;
+    /// The floors come back around in endless mode
+    pub fn get_floor(&self, floor: usize, mode: RunMode) -> Option<&Floor> {
+        match mode {
+            RunMode::Endless if !self.floors.is_empty() => {
+                self.floors.get(floor % self.floors.len())
+            }
+            _ => self.floors.get(floor),
+        }
+    }
+
+    pub fn load_art(&mut self) {
+        for enemy in self.enemies.iter_mut() {
+            enemy.load_art();
+        }
+        for guardian in self
+            .floors
+            .iter_mut()
+            .filter_map(|floor| floor.guardian.as_mut())
+        {
+            guardian.load_art();
+        }
+        self.rules.boss.load_art();
     }
 }
 
@@ -143,12 +604,15 @@ pub enum Directions {
     RightFront,
     Right,
     Front,
+    LeftRight,
+    DeadEnd,
 }
 
 pub enum Movement {
     Forward,
     Left,
     Right,
+    Back,
 }
 
 impl Movement {
@@ -157,6 +621,7 @@ impl Movement {
             "forward" => Some(Movement::Forward),
             "left" => Some(Movement::Left),
             "right" => Some(Movement::Right),
+            "back" => Some(Movement::Back),
             _ => None,
         }
     }
@@ -171,18 +636,8 @@ impl Directions {
             Directions::RightFront => art::RIGHT_FRONT,
             Directions::Right => art::RIGHT,
             Directions::Front => art::FRONT,
-        }
-    }
-
-    pub fn get_random_direction() -> Directions {
-        let index = rand::thread_rng().gen_range(0..6);
-        match index {
-            0 => Directions::All,
-            1 => Directions::LeftFront,
-            2 => Directions::Left,
-            3 => Directions::RightFront,
-            4 => Directions::Right,
-            _ => Directions::Front,
+            Directions::LeftRight => art::LEFT_RIGHT,
+            Directions::DeadEnd => art::DEAD_END,
         }
     }
 
@@ -194,37 +649,8 @@ impl Directions {
             Directions::RightFront => "Forward, Right".to_string(),
             Directions::Right => "Right".to_string(),
             Directions::Front => "Forward".to_string(),
+            Directions::LeftRight => "Left, Right".to_string(),
+            Directions::DeadEnd => "".to_string(),
         }
     }
-
-    pub fn can_go_direction(&self, mov: Movement) -> bool {
-        if self == &Directions::All {
-            return true;
-        }
-
-        match mov {
-            Movement::Forward => {
-                if self == &Directions::LeftFront
-                    || self == &Directions::RightFront
-                    || self == &Directions::Front
-                {
-                    return true;
-                }
-            }
-
-            Movement::Left => {
-                if self == &Directions::LeftFront || self == &Directions::Left {
-                    return true;
-                }
-            }
-
-            Movement::Right => {
-                if self == &Directions::RightFront || self == &Directions::Right {
-                    return true;
-                }
-            }
-        }
-
-        false
-    }
 }
@@ -0,0 +1,125 @@
+use rand::{prelude::SliceRandom, Rng};
+use serde::Deserialize;
+
+use super::{data::PlayerStats, effects::StatusEffect, items::ItemType};
+
+/// A conversation with an NPC, defined in the labyrinth data file
+#[derive(Debug, Deserialize, Clone)]
+pub struct Dialogue {
+    // sprites of the NPCs that can start it, any NPC if empty
+    #[serde(default)]
+    pub npcs: Vec<usize>,
+    // the conversation starts at the first one
+    pub nodes: Vec<DialogueNode>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DialogueNode {
+    pub id: String,
+    // what the NPC says, "{name}" is replaced by their username
+    pub line: String,
+    // the conversation ends on a node without choices
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DialogueChoice {
+    pub text: String,
+    // id of the node it leads to, the conversation ends without one
+    #[serde(default)]
+    pub next: Option<String>,
+    #[serde(default)]
+    pub condition: Option<DialogueCondition>,
+    #[serde(default)]
+    pub effects: Vec<DialogueEffect>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum DialogueCondition {
+    Gold(usize),
+    Item(ItemType),
+    Level(usize),
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum DialogueEffect {
+    // permanent bonus to the stats of the player
+    Boon {
+        #[serde(default)]
+        health: f32,
+        #[serde(default)]
+        damages: f32,
+    },
+    // name of an item of the items list
+    GiveItem(String),
+    GiveGold(usize),
+    TakeGold(usize),
+    Effect(StatusEffect),
+    // added to the relationship with the NPC
+    Relationship(i32),
+    // the NPC will remember it in the real world
+    Enemy,
+    Friend,
+}
+
+/// Where the player is in a conversation
+#[derive(Debug, Clone, Copy)]
+pub struct DialogueState {
+    pub dialogue: usize,
+    pub node: usize,
+}
+
+impl DialogueCondition {
+    pub fn is_met(&self, player: &PlayerStats) -> bool {
+        match self {
+            DialogueCondition::Gold(gold) => player.gold >= *gold,
+            DialogueCondition::Item(kind) => player.count_items(*kind) > 0,
+            DialogueCondition::Level(level) => player.level >= *level,
+        }
+    }
+
+    pub fn to_display(&self) -> String {
+        match self {
+            DialogueCondition::Gold(gold) => format!("needs {} gold", gold),
+            DialogueCondition::Item(kind) => format!("needs a {}", kind.to_display_str()),
+            DialogueCondition::Level(level) => format!("needs level {}", level),
+        }
+    }
+}
+
+impl Dialogue {
+    /// Picks one of the conversations this NPC can have
+    pub fn get_random_for(
+        dialogues: &[Dialogue],
+        sprite_id: usize,
+        rng: &mut impl Rng,
+    ) -> Option<usize> {
+        let own: Vec<usize> = (0..dialogues.len())
+            .filter(|index| dialogues[*index].npcs.contains(&sprite_id))
+            .collect();
+        let anyone: Vec<usize> = (0..dialogues.len())
+            .filter(|index| dialogues[*index].npcs.is_empty())
+            .collect();
+
+        // the NPC has its own things to say, or small talk otherwise
+        let candidates = if own.is_empty() { anyone } else { own };
+        candidates.choose(rng).copied()
+    }
+
+    pub fn find_node(&self, id: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.id == id)
+    }
+}
+
+impl DialogueNode {
+    pub fn get_line(&self, username: &str) -> String {
+        self.line.replace("{name}", username)
+    }
+}
+
+impl DialogueChoice {
+    pub fn get_text(&self, username: &str) -> String {
+        self.text.replace("{name}", username)
+    }
+}
@@ -0,0 +1,180 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum EffectType {
+    // loses `power` HP each turn
+    Poison,
+    Bleeding,
+    // can't act
+    Stun,
+    // gains `power` HP each turn
+    Regeneration,
+    // absorbs `power` damages of each hit
+    Shield,
+}
+
+impl EffectType {
+    pub fn to_display_str(self) -> &'static str {
+        match self {
+            EffectType::Poison => "Poisoned",
+            EffectType::Bleeding => "Bleeding",
+            EffectType::Stun => "Stunned",
+            EffectType::Regeneration => "Regenerating",
+            EffectType::Shield => "Shielded",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct StatusEffect {
+    pub kind: EffectType,
+    // number of turns left
+    pub turns: usize,
+    #[serde(default)]
+    pub power: f32,
+}
+
+/// The effects a fighter is under, the player or an enemy
+#[derive(Debug, Clone, Default)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+    // a fighter gets one turn to act after a stun before it can be stunned again
+    pub stun_immunity: usize,
+}
+
+impl StatusEffects {
+    /// The same effect twice doesn't stack, it's refreshed instead.
+    /// A stun doesn't hold on a fighter that is or just was stunned,
+    /// returns false when the effect didn't hold
+    pub fn apply(&mut self, effect: StatusEffect) -> bool {
+        if effect.kind == EffectType::Stun && (self.has(EffectType::Stun) || self.stun_immunity > 0)
+        {
+            return false;
+        }
+
+        match self.effects.iter_mut().find(|e| e.kind == effect.kind) {
+            Some(current) => {
+                current.turns = current.turns.max(effect.turns);
+                current.power = current.power.max(effect.power);
+            }
+            None => self.effects.push(effect),
+        }
+        true
+    }
+
+    pub fn has(&self, kind: EffectType) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    pub fn cure(&mut self, kind: EffectType) -> bool {
+        let count = self.effects.len();
+        self.effects.retain(|effect| effect.kind != kind);
+        count != self.effects.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+        self.stun_immunity = 0;
+    }
+
+    // what is left of a hit once the shield took its part
+    pub fn absorb(&self, damages: f32) -> f32 {
+        self.effects
+            .iter()
+            .filter(|effect| effect.kind == EffectType::Shield)
+            .fold(damages, |damages, shield| (damages - shield.power).max(0.0))
+    }
+
+    /// Applies the effects for one turn and removes the ones that are over
+    pub fn tick(&mut self, name: &str, health: &mut f32, max_health: f32) -> Vec<String> {
+        let mut res = Vec::new();
+        self.stun_immunity = self.stun_immunity.saturating_sub(1);
+
+        for effect in self.effects.iter_mut() {
+            match effect.kind {
+                EffectType::Poison | EffectType::Bleeding => {
+                    *health = (*health - effect.power).max(0.0);
+                    res.push(format!(
+                        "{} loses {} HP ({})",
+                        name,
+                        effect.power,
+                        effect.kind.to_display_str().to_lowercase()
+                    ));
+                }
+                EffectType::Regeneration => {
+                    *health = (*health + effect.power).min(max_health);
+                    res.push(format!("{} regenerates {} HP", name, effect.power));
+                }
+                _ => (),
+            }
+            effect.turns = effect.turns.saturating_sub(1);
+        }
+
+        for effect in self.effects.iter().filter(|effect| effect.turns == 0) {
+            if effect.kind == EffectType::Stun {
+                self.stun_immunity = 1;
+            }
+            res.push(format!(
+                "{} is no longer {}",
+                name,
+                effect.kind.to_display_str().to_lowercase()
+            ));
+        }
+        self.effects.retain(|effect| effect.turns > 0);
+
+        res
+    }
+
+    pub fn to_display(&self) -> String {
+        if self.effects.is_empty() {
+            return "None".to_string();
+        }
+
+        self.effects
+            .iter()
+            .map(|effect| format!("{} ({})", effect.kind.to_display_str(), effect.turns))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STUN: StatusEffect = StatusEffect {
+        kind: EffectType::Stun,
+        turns: 1,
+        power: 0.0,
+    };
+
+    #[test]
+    fn enemy_hit_every_turn_still_acts() {
+        let mut effects = StatusEffects::default();
+        let mut health = 10.0;
+
+        // the order of a fight: the hit of the player, then the turn of the enemy
+        let mut acted = 0;
+        for _ in 0..10 {
+            effects.apply(STUN);
+            if !effects.has(EffectType::Stun) {
+                acted += 1;
+            }
+            effects.tick("The rat", &mut health, 10.0);
+        }
+        assert_eq!(acted, 5);
+    }
+
+    #[test]
+    fn stun_holds_again_after_a_free_turn() {
+        let mut effects = StatusEffects::default();
+        let mut health = 10.0;
+
+        assert!(effects.apply(STUN));
+        assert!(!effects.apply(STUN));
+        effects.tick("The rat", &mut health, 10.0);
+        assert!(!effects.apply(STUN));
+        effects.tick("The rat", &mut health, 10.0);
+        assert!(effects.apply(STUN));
+    }
+}
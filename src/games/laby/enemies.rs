@@ -1,59 +1,329 @@
-use rand::Rng;
+use bevy::prelude::*;
+use rand::{prelude::SliceRandom, Rng};
 use serde::Deserialize;
 
-use super::art;
+use super::{
+    art,
+    data::LevelBonus,
+    effects::{StatusEffect, StatusEffects},
+};
+
+// the ascii art of the enemies, embedded in the game like the data file
+const ART_FILES: [(&str, &str); 6] = [
+    ("bat.txt", include_str!("../../../assets/laby/bat.txt")),
+    ("boss.txt", include_str!("../../../assets/laby/boss.txt")),
+    (
+        "gargoyle.txt",
+        include_str!("../../../assets/laby/gargoyle.txt"),
+    ),
+    ("rat.txt", include_str!("../../../assets/laby/rat.txt")),
+    (
+        "spider.txt",
+        include_str!("../../../assets/laby/spider.txt"),
+    ),
+    (
+        "wraith.txt",
+        include_str!("../../../assets/laby/wraith.txt"),
+    ),
+];
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Enemy {
     pub kind: EnemyType,
+    pub name: String,
     pub description: String,
     pub health: f32,
     pub max_health: f32,
     pub exp: usize,
     pub damages: f32,
+    #[serde(default)]
+    pub gold: usize,
+    // name of the file in the art folder
+    #[serde(default)]
+    pub art_file: String,
+    // content of the art file, loaded with the game
+    #[serde(skip)]
+    pub art: String,
+    #[serde(default)]
+    pub loot: Vec<LootEntry>,
+    // the enemy only shows up between these depths (inclusive)
+    #[serde(default = "default_spawn_depth")]
+    pub spawn_depth: (usize, usize),
+    // how likely each action is to be chosen
+    #[serde(default = "default_behavior")]
+    pub behavior: Vec<(EnemyAction, u32)>,
+    #[serde(default)]
+    pub special: Option<SpecialAbility>,
+    // the enemy changes when its health goes low
+    #[serde(default)]
+    pub phases: Vec<EnemyPhase>,
+    // what the enemy will do on its next turn, shown to the player
+    #[serde(skip)]
+    pub intention: EnemyAction,
+    #[serde(skip)]
+    pub is_charged: bool,
+    #[serde(skip)]
+    pub has_fled: bool,
+    #[serde(skip)]
+    pub effects: StatusEffects,
+    // username of the NPC when the enemy is another player
+    #[serde(skip)]
+    pub rival: Option<String>,
+    // number of phases already entered
+    #[serde(skip)]
+    pub phase: usize,
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq)]
-pub enum EnemyType {
-    Rat,
-    Bat,
-    Boss,
+fn default_spawn_depth() -> (usize, usize) {
+    (0, usize::MAX)
 }
 
-impl EnemyType {
-    pub fn _to_display_str(&self) -> &str {
-        match self {
-            EnemyType::Rat => "rat",
-            EnemyType::Bat => "bat",
-            EnemyType::Boss => "boss",
-        }
-    }
+fn default_behavior() -> Vec<(EnemyAction, u32)> {
+    vec![(EnemyAction::Attack, 1)]
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum EnemyAction {
+    #[default]
+    Attack,
+    // no damages this turn, the next attack deals twice the damages
+    Charge,
+    // takes half the damages this turn
+    Defend,
+    // runs away when wounded, the player gets nothing
+    Flee,
+    Special,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpecialAbility {
+    pub name: String,
+    // multiplies the damages of the enemy
+    #[serde(default)]
+    pub damages: f32,
+    // health given back to the enemy
+    #[serde(default)]
+    pub heal: f32,
+    // given to the player
+    #[serde(default)]
+    pub effect: Option<StatusEffect>,
+    // given to the enemy itself
+    #[serde(default)]
+    pub self_effect: Option<StatusEffect>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EnemyPhase {
+    // the phase starts when health / max_health goes below it
+    pub health_ratio: f32,
+    pub message: String,
+    pub damages: f32,
+    pub behavior: Vec<(EnemyAction, u32)>,
+}
+
+/// The NPCs who hate the player come after them in the labyrinth
+#[derive(Debug, Deserialize)]
+pub struct RivalRules {
+    // chance to meet one in a new room
+    pub chance: f64,
+    pub min_depth: usize,
+    // stats of a rival for a player of level 1, "{name}" is replaced
+    // by the username in the description
+    pub enemy: Enemy,
+    // added for each level of the player
+    pub level_bonus: LevelBonus,
+    // added for each room between the entrance and the rival
+    pub depth_bonus: LevelBonus,
+}
+
+/// Identifies a kind of enemy ("rat", "bat"...), defined in the data file
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct EnemyType(pub String);
+
+/// An item the enemy may drop when killed
+#[derive(Debug, Deserialize, Clone)]
+pub struct LootEntry {
+    // name of an item of the items list
+    pub item: String,
+    // between 0 and 1
+    pub chance: f64,
 }
 
 impl Enemy {
     pub fn get_ascii_art(&self) -> &str {
-        match self.kind {
-            EnemyType::Rat => art::RAT,
-            EnemyType::Bat => art::BAT,
-            EnemyType::Boss => art::BOSS,
+        if self.art.is_empty() {
+            art::UNKNOWN_ENEMY
+        } else {
+            &self.art
         }
     }
 
-    pub fn get_random_enemy(enemies: &[Enemy]) -> &Enemy {
-        let index = rand::thread_rng().gen_range(0..enemies.len());
-        return enemies.get(index).unwrap();
+    /// Picks what the enemy will do on its next turn
+    pub fn choose_intention(&mut self, rng: &mut impl Rng) {
+        if self.is_charged {
+            self.intention = EnemyAction::Attack;
+            return;
+        }
+
+        let is_wounded = self.health < self.max_health / 2.0;
+        let has_special = self.special.is_some();
+        let actions: Vec<&(EnemyAction, u32)> = self
+            .behavior
+            .iter()
+            .filter(|(action, _)| match action {
+                EnemyAction::Flee => is_wounded,
+                EnemyAction::Special => has_special,
+                _ => true,
+            })
+            .collect();
+
+        self.intention = actions
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .map(|(action, _)| *action)
+            .unwrap_or(EnemyAction::Attack);
+    }
+
+    /// Telegraphs the next move so the player can prepare or protect
+    pub fn get_intention_display(&self) -> String {
+        match self.intention {
+            EnemyAction::Attack if self.is_charged => format!(
+                "The {} unleashes its charged attack! ({} dmg)",
+                self.name,
+                self.damages * 2.0
+            ),
+            EnemyAction::Attack => {
+                format!(
+                    "The {} is about to attack ({} dmg)",
+                    self.name, self.damages
+                )
+            }
+            EnemyAction::Charge => {
+                format!("The {} is gathering strength for a big hit...", self.name)
+            }
+            EnemyAction::Defend => format!("The {} is on its guard (x0.5 dmg)", self.name),
+            EnemyAction::Flee => format!("The {} looks for a way out...", self.name),
+            EnemyAction::Special => match &self.special {
+                Some(special) => format!("The {} prepares {}!", self.name, special.name),
+                None => format!("The {} hesitates...", self.name),
+            },
+        }
+    }
+
+    /// Enters the next phase if the health is low enough
+    pub fn update_phase(&mut self) -> Option<String> {
+        let phase = self.phases.get(self.phase)?;
+        if self.health <= 0.0 || self.health / self.max_health > phase.health_ratio {
+            return None;
+        }
+
+        self.damages = phase.damages;
+        self.behavior = phase.behavior.clone();
+        self.phase += 1;
+
+        Some(self.phases[self.phase - 1].message.clone())
+    }
+
+    /// Takes the art of its file, an unknown file gives the default art
+    pub fn load_art(&mut self) {
+        if self.art_file.is_empty() {
+            return;
+        }
+
+        match ART_FILES.iter().find(|(name, _)| *name == self.art_file) {
+            Some((_, content)) => self.art = format!("\n{}", content),
+            None => {
+                warn!(
+                    "Unknown art file {:?} for the enemy {:?}",
+                    self.art_file, self.kind
+                );
+                self.art.clear();
+            }
+        }
+    }
+
+    /// Makes the enemy as strong as the progress of the player,
+    /// the data of the enemies stays untouched
+    pub fn scale(
+        &mut self,
+        level_bonus: &LevelBonus,
+        level: usize,
+        depth_bonus: &LevelBonus,
+        depth: usize,
+    ) {
+        let (levels, depth) = ((level - 1) as f32, depth as f32);
+
+        self.max_health += level_bonus.max_health * levels + depth_bonus.max_health * depth;
+        self.health = self.max_health;
+        self.damages += level_bonus.damages * levels + depth_bonus.damages * depth;
+    }
+
+    /// A hostile NPC as strong as the progress of the player
+    pub fn new_rival(username: &str, rules: &RivalRules, level: usize, depth: usize) -> Enemy {
+        let mut enemy = rules.enemy.clone();
+
+        enemy.scale(&rules.level_bonus, level, &rules.depth_bonus, depth);
+        enemy.name = username.to_string();
+        enemy.description = enemy.description.replace("{name}", username);
+        enemy.art = art::KNIGHT.to_string();
+        enemy.rival = Some(username.to_string());
+        enemy
+    }
+
+    /// Picks an enemy of the pool that can show up at this depth,
+    /// or any enemy of the pool if none of them can,
+    /// an empty pool allows every enemy
+    pub fn get_random_enemy<'a>(
+        enemies: &'a [Enemy],
+        pool: &[EnemyType],
+        depth: usize,
+        rng: &mut impl Rng,
+    ) -> &'a Enemy {
+        let mut pool: Vec<&Enemy> = enemies
+            .iter()
+            .filter(|enemy| pool.is_empty() || pool.contains(&enemy.kind))
+            .collect();
+        if pool.is_empty() {
+            pool = enemies.iter().collect();
+        }
+
+        let candidates: Vec<&Enemy> = pool
+            .iter()
+            .copied()
+            .filter(|enemy| (enemy.spawn_depth.0..=enemy.spawn_depth.1).contains(&depth))
+            .collect();
+
+        match candidates.choose(rng) {
+            Some(enemy) => enemy,
+            None => pool.choose(rng).unwrap(),
+        }
     }
 }
 
 impl Default for Enemy {
     fn default() -> Self {
         Enemy {
-            kind: EnemyType::Rat,
+            kind: EnemyType("rat".to_string()),
+            name: "Rat".to_string(),
             description: "Rat description".to_string(),
             max_health: 3.0,
             health: 3.0,
             exp: 5,
             damages: 0.5,
+            gold: 0,
+            art_file: String::new(),
+            art: String::new(),
+            loot: Vec::new(),
+            spawn_depth: default_spawn_depth(),
+            behavior: default_behavior(),
+            special: None,
+            phases: Vec::new(),
+            intention: EnemyAction::Attack,
+            is_charged: false,
+            has_fled: false,
+            effects: StatusEffects::default(),
+            rival: None,
+            phase: 0,
         }
     }
 }
@@ -0,0 +1,35 @@
+use serde::Deserialize;
+
+use super::{
+    data::LevelBonus,
+    enemies::{Enemy, EnemyType},
+};
+
+/// A level of the labyrinth, the stairs of the last one lead to the boss
+#[derive(Debug, Deserialize)]
+pub struct Floor {
+    pub name: String,
+    // shown when the player arrives
+    pub intro: String,
+    // the corridors of this floor, the common ones if empty
+    #[serde(default)]
+    pub descriptions: Vec<String>,
+    // the kinds of enemies living here, all of them if empty
+    #[serde(default)]
+    pub enemies: Vec<EnemyType>,
+    // added to the enemies met on this floor
+    #[serde(default)]
+    pub enemy_bonus: LevelBonus,
+    // waits at the exit and guards the stairs
+    #[serde(default)]
+    pub guardian: Option<Enemy>,
+}
+
+impl Floor {
+    /// The enemies of the deeper floors are stronger
+    pub fn strengthen(&self, enemy: &mut Enemy) {
+        enemy.max_health += self.enemy_bonus.max_health;
+        enemy.health = (enemy.health + self.enemy_bonus.health).min(enemy.max_health);
+        enemy.damages += self.enemy_bonus.damages;
+    }
+}
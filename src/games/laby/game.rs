@@ -1,68 +1,116 @@
-use core::time;
-use std::thread;
+use std::{ops::RangeInclusive, time::Instant};
 
 use bevy::prelude::*;
-use rand::{prelude::SliceRandom, Rng};
+use rand::{prelude::SliceRandom, rngs::StdRng, Rng};
 
 use crate::{
     console::{event::PrintConsoleEvent, ConsoleData},
     games::{
         laby::{
             art,
-            data::{Directions, PlayerActions},
+            data::{Directions, Movement, PlayerActions},
             utils::{self, display_bar},
         },
         ConsoleGamesData, GameList,
     },
-    npcs::{NPCData, NPCsResource},
+    misc::day_cycle::DayCycleResource,
+    npcs::{Interaction, NPCData, NPCsResource},
     vulnerability::{BoolVulnerabilityType, VulnerabilityResource},
 };
 
 use super::{
-    data::{GameState, LabyrinthData, LabyrinthResourceFile, PlayerStats, RoomType},
-    enemies::{Enemy, EnemyType},
-    items::ItemType,
+    data::{
+        GameState, LabyrinthData, LabyrinthResourceFile, LabyrinthRng, LevelBonus, PlayerStats,
+        RoomType, RunMode, RunOptions, RunStats,
+    },
+    enemies::Enemy,
+    items::{Item, ItemType},
+    maze::{Cardinal, Maze},
+    quests::{Quest, QuestGoal},
+    skills::SkillKind,
+    summary::{
+        display_duration, display_game_time, display_splits, LabyrinthHistory, RunEnding,
+        RunSummary, Split,
+    },
 };
 
-const NUMBER_OF_TURN_TO_WIN: usize = 30;
+// how long it takes to walk to the next room, in seconds
+const WALKING_TIME: RangeInclusive<f32> = 0.2..=0.7;
+// time between two steps of the dots animation
+const WALKING_DOTS_TIME: f32 = 0.15;
 
 pub fn game_loop(
     mut laby_data: ResMut<LabyrinthData>,
-    mut laby_res: ResMut<LabyrinthResourceFile>,
+    laby_res: Res<LabyrinthResourceFile>,
     mut console_writer: EventWriter<PrintConsoleEvent>,
     mut console_data: ResMut<ConsoleData>,
     mut player: ResMut<PlayerStats>,
     mut vuln_res: ResMut<VulnerabilityResource>,
-    npc_res: Res<NPCsResource>,
     mut cg_data: ResMut<ConsoleGamesData>,
+    mut laby_rng: ResMut<LabyrinthRng>,
+    mut history: ResMut<LabyrinthHistory>,
 ) {
     if laby_data.has_shown_turn_infos || laby_data.wait_for_continue {
         return;
     }
 
     if player.health <= 0.0 {
-        *vuln_res
+        let is_lost = *vuln_res
+            .bool_vulnerabilities
+            .get(&BoolVulnerabilityType::LabyrinthLosing)
+            .unwrap();
+        if !is_lost && laby_data.room_type == RoomType::Enemy {
+            let msg = format!("Killed by the {}", laby_data.enemy.name);
+            laby_data.add_log(&msg);
+
+            // the rival will brag about it
+            if let Some(username) = laby_data.enemy.rival.clone() {
+                vuln_res
+                    .relationships
+                    .interact(&username, Interaction::RivalWon);
+                laby_data
+                    .journal
+                    .on_interaction(&username, Interaction::RivalWon);
+            }
+        } else if !is_lost {
+            laby_data.add_log("Died of your wounds");
+        }
+
+        let has_lost = vuln_res
             .bool_vulnerabilities
             .get_mut(&BoolVulnerabilityType::LabyrinthLosing)
-            .unwrap() = true;
+            .unwrap();
+        if !*has_lost {
+            console_writer.send(PrintConsoleEvent(end_run(
+                &mut laby_data,
+                &player,
+                &laby_rng,
+                &mut history,
+                RunEnding::Death,
+            )));
+        }
+        *has_lost = true;
         return;
     }
 
-    if player.exp >= 10 {
-        player.exp -= 10;
-        player.level += 1;
-        player.max_health += 2.0;
-        player.damages += 1.0;
-        player.health += 2.0;
-        player.health = player.health.min(player.max_health);
-
-        for enemy in laby_res.enemies.iter_mut() {
-            enemy.max_health += 2.0;
-            enemy.health = (enemy.health + 2.0).min(enemy.max_health);
-            enemy.damages += 0.5;
+    if level_up(&mut player, &laby_res) {
+        let msg = format!(
+            "\nLevel up! You are now level {}, with {} skill points to spend",
+            player.level, player.skill_points
+        );
+        laby_data.status_message.push_str(&msg);
+        laby_data.add_log(&format!("Reached level {}", player.level));
+        // the points are spent between two fights
+        if laby_data.game_state == GameState::Exploring && laby_data.room_type != RoomType::Enemy {
+            laby_data.game_state = GameState::LevelUp;
         }
     }
 
+    // the rewards of the quests completed since the last pass
+    for msg in claim_quest_rewards(&mut laby_data, &laby_res, &mut player, &mut vuln_res) {
+        laby_data.status_message.push_str(&format!("\n{}", msg));
+    }
+
     // first we clear the screen
     console_data.messages.clear();
 
@@ -76,50 +124,169 @@ pub fn game_loop(
             laby_data.wait_for_continue = true;
         }
 
+        GameState::LevelUp => {
+            console_writer.send(PrintConsoleEvent(display_level_up(&laby_res, &player)));
+            console_writer.send(PrintConsoleEvent(display_status(&laby_data)));
+            laby_data.wait_for_continue = true;
+        }
+
+        // picking a class before the first room
+        GameState::CharacterCreation => {
+            console_writer.send(PrintConsoleEvent(display_classes(&laby_res)));
+            laby_data.wait_for_continue = true;
+        }
+
         // if it's just about exploring
         GameState::Exploring => {
             match laby_data.room_type {
                 RoomType::Corridor => {
-                    console_writer.send(PrintConsoleEvent(turn_display(&laby_data)))
+                    console_writer.send(PrintConsoleEvent(turn_display(&laby_data, &laby_res)))
                 }
 
                 RoomType::Enemy => {
+                    // no gold nor loot, but the fight still teaches something
+                    if laby_data.enemy.has_fled {
+                        let exp = laby_data.enemy.exp / 2;
+                        let msg = format!(
+                            "\nThe {} got away, you only get {} exp...",
+                            laby_data.enemy.name, exp
+                        );
+                        laby_data.status_message.push_str(&msg);
+                        let msg = format!("The {} got away", laby_data.enemy.name);
+                        laby_data.add_log(&msg);
+                        player.exp += exp;
+                        player.action = PlayerActions::Attack;
+                        laby_data.wait_for_continue = false;
+                        laby_data.has_shown_turn_infos = false;
+                        clear_room(&mut laby_data, &laby_res, &mut laby_rng);
+                        return;
+                    }
+
                     if laby_data.enemy.health <= 0.0 {
                         laby_data.enemy.health = laby_data.enemy.health.max(0.0);
                         laby_data.wait_for_continue = false;
                         laby_data.has_shown_turn_infos = false;
 
-                        if laby_data.enemy.kind == EnemyType::Boss {
+                        if laby_data.enemy.kind == laby_res.rules.boss.kind {
                             console_writer.send(PrintConsoleEvent(
                                 "Congrats! You beat the game!\n".to_string(),
                             ));
+                            let msg = format!("Defeated the {}", laby_data.enemy.name);
+                            laby_data.add_log(&msg);
+                            push_split(&mut laby_data, &laby_res);
+                            console_writer.send(PrintConsoleEvent(end_run(
+                                &mut laby_data,
+                                &player,
+                                &laby_rng,
+                                &mut history,
+                                RunEnding::Victory,
+                            )));
                             cg_data.loaded_game = GameList::None;
                             cg_data.has_won_laby = true;
                             laby_data.reset();
-                            laby_res.reset();
                             player.reset();
                             return;
                         }
 
                         laby_data.status_message = format!(
-                            "Enemy killed! Congrats!\nYou gained {} Exp",
-                            laby_data.enemy.exp
+                            "{} killed! Congrats!\nYou gained {} Exp and {} gold",
+                            laby_data.enemy.name, laby_data.enemy.exp, laby_data.enemy.gold
                         );
+                        let enemy = laby_data.enemy.clone();
+                        laby_data.journal.on_kill(&enemy);
+                        // only the guardian of the floor waits at the exit
+                        if laby_data.position == laby_data.maze.exit {
+                            laby_data.add_log(&format!(
+                                "Beat {}, the guardian of the stairs",
+                                enemy.name
+                            ));
+                            laby_data.is_stairs_open = true;
+                            laby_data
+                                .status_message
+                                .push_str("\nThe stairs to the next floor are free!");
+                        }
+                        for msg in roll_enemy_loot(
+                            &enemy,
+                            &laby_res,
+                            &mut player,
+                            &mut laby_rng,
+                            &mut laby_data.run_stats,
+                        ) {
+                            laby_data.status_message.push_str(&format!("\n{}", msg));
+                        }
+                        let kind = laby_data.enemy.kind.clone();
+                        let completed: Vec<String> = laby_data
+                            .quests
+                            .iter_mut()
+                            .filter_map(|quest| quest.on_kill(&kind).then(|| quest.giver.clone()))
+                            .collect();
+                        for giver in completed {
+                            let msg = format!("\nQuest completed for {}!", giver);
+                            laby_data.status_message.push_str(&msg);
+                        }
+                        if let Some(username) = laby_data.enemy.rival.clone() {
+                            vuln_res
+                                .relationships
+                                .interact(&username, Interaction::RivalBeaten);
+                            laby_data.add_log(&format!("Beat {} in a fight", username));
+                            let msg = format!(
+                                "\n{} logs off in shame, they'll think twice before coming after you",
+                                username
+                            );
+                            laby_data.status_message.push_str(&msg);
+                        }
                         player.exp += laby_data.enemy.exp;
+                        player.gold += laby_data.enemy.gold;
+                        let heal = player.get_skill_bonus(SkillKind::Vampirism);
+                        if heal > 0.0 {
+                            player.health = (player.health + heal).min(player.max_health);
+                            let msg = format!("\nYou drain {} HP from the corpse", heal);
+                            laby_data.status_message.push_str(&msg);
+                        }
+                        for companion in player.party.iter_mut() {
+                            if companion.gain_exp(laby_data.enemy.exp, &laby_res.rules.companion) {
+                                let msg = format!(
+                                    "\n{} is now level {}",
+                                    companion.username, companion.level
+                                );
+                                laby_data.status_message.push_str(&msg);
+                            }
+                        }
                         player.action = PlayerActions::Attack;
 
-                        new_turn(&mut laby_data, &laby_res, &mut player, &npc_res);
+                        clear_room(&mut laby_data, &laby_res, &mut laby_rng);
                         return;
                     }
 
-                    console_writer.send(PrintConsoleEvent(enemy_display(&laby_data)));
+                    console_writer.send(PrintConsoleEvent(enemy_display(&laby_data, &player)));
                 }
 
                 RoomType::Item => console_writer.send(PrintConsoleEvent(item_display(&laby_data))),
 
-                RoomType::Npc => console_writer.send(PrintConsoleEvent(npc_display(&laby_data))),
+                RoomType::Npc => console_writer.send(PrintConsoleEvent(npc_display(
+                    &laby_data, &laby_res, &player, &vuln_res,
+                ))),
+
+                RoomType::Merchant => {
+                    console_writer.send(PrintConsoleEvent(merchant_display(&laby_data)))
+                }
+
+                RoomType::Riddle => {
+                    console_writer.send(PrintConsoleEvent(riddle_display(&laby_data, &laby_res)))
+                }
+
+                RoomType::Trap => {
+                    console_writer.send(PrintConsoleEvent(trap_display(&laby_data, &laby_res)))
+                }
+
+                RoomType::Gate => console_writer.send(PrintConsoleEvent(gate_display(
+                    &laby_data, &laby_res, &player,
+                ))),
             };
             console_writer.send(PrintConsoleEvent(player_infos(&player)));
+            if laby_data.mode == RunMode::Speedrun {
+                console_writer.send(PrintConsoleEvent(display_timer(&laby_data)));
+            }
 
             console_writer.send(PrintConsoleEvent(display_status(&laby_data)));
 
@@ -140,7 +307,43 @@ fn display_status(laby_data: &ResMut<LabyrinthData>) -> String {
     res
 }
 
-fn display_tutorial(laby_res: &ResMut<LabyrinthResourceFile>, page: usize) -> String {
+/// Prints the summary of the run and keeps it in the history
+pub fn end_run(
+    laby_data: &mut LabyrinthData,
+    player: &PlayerStats,
+    laby_rng: &LabyrinthRng,
+    history: &mut ResMut<LabyrinthHistory>,
+    ending: RunEnding,
+) -> String {
+    laby_data.run_stats.stop_clock();
+    let summary = RunSummary::new(laby_data, player, laby_rng.seed, ending);
+    let res = summary.to_display();
+    history.runs.push(summary);
+
+    res
+}
+
+// the clock of the speedrun, shown under the player stats on each turn
+fn display_timer(laby_data: &ResMut<LabyrinthData>) -> String {
+    let stats = &laby_data.run_stats;
+    let mut res = format!(
+        "Timer: {} (in-game {})\n",
+        display_duration(stats.get_duration()),
+        display_game_time(stats.game_time)
+    );
+    res.push_str(&display_splits(&stats.splits));
+
+    res
+}
+
+pub fn display_seed(laby_rng: &ResMut<LabyrinthRng>) -> String {
+    format!(
+        "Seed of this run: {} (replay it with 'play labyrinth --seed {}')",
+        laby_rng.seed, laby_rng.seed
+    )
+}
+
+fn display_tutorial(laby_res: &Res<LabyrinthResourceFile>, page: usize) -> String {
     let mut res = String::from("------------------==[Labyrinth]==-----------------\n\n");
 
     res.push_str(laby_res.tutorial.get(page).unwrap());
@@ -149,26 +352,70 @@ fn display_tutorial(laby_res: &ResMut<LabyrinthResourceFile>, page: usize) -> St
     res.push_str("Don't forget to type 'help' for the list of commands.\n");
     res.push_str("To show this tutorial type 'tutorial', to show the\n");
     res.push_str("Information about the current room type 'infos'\n");
-    res.push_str("To navigate in the labyrinth type 'go <dir>'\n\n");
+    res.push_str("To navigate in the labyrinth type 'go <dir>'\n");
+    res.push_str("To see the part of the maze you explored type 'map'\n\n");
     res.push_str("Type 'continue' in order to start the adventure...\n");
 
     res
 }
 
-fn turn_display(laby_data: &ResMut<LabyrinthData>) -> String {
+fn display_classes(laby_res: &Res<LabyrinthResourceFile>) -> String {
+    let mut res = String::from("---------------[Character Creation]---------------\n\n");
+    res.push_str("Who are you, adventurer?\n\n");
+    for (index, class) in laby_res.classes.iter().enumerate() {
+        res.push_str(&format!("{}. {}\n\n", index + 1, class.to_display()));
+    }
+    res.push_str("Type 'choose <class>' to enter the labyrinth...\n");
+
+    res
+}
+
+fn turn_display(
+    laby_data: &ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+) -> String {
     // Map display
     let mut res = String::from("----------------------[View]----------------------\n");
     res.push_str(laby_data.next_directions.get_ascii_art());
     res.push('\n');
+    let floor_count = match laby_data.mode {
+        RunMode::Endless => String::new(),
+        _ => format!("/{}", laby_res.floors.len().max(1)),
+    };
     res.push_str(&format!(
-        "Room number: {}/{}\n",
-        laby_data.steps_number, NUMBER_OF_TURN_TO_WIN
+        "Floor {}{}{} - Depth {}\n",
+        laby_data.floor + 1,
+        floor_count,
+        laby_res
+            .get_floor(laby_data.floor, laby_data.mode)
+            .map(|floor| format!(" ({})", floor.name))
+            .unwrap_or_default(),
+        laby_data.maze.get_depth(laby_data.position)
     ));
+    if laby_data.mode == RunMode::Endless {
+        res.push_str(&format!(
+            "Depth score: {}\n",
+            laby_data.run_stats.depth_score
+        ));
+    }
     res.push_str(&format!(
-        "Available movements: [{}]\n\n",
-        laby_data.next_directions.to_display()
+        "Rooms explored: {}/{}\n",
+        laby_data.maze.visited_count(),
+        laby_data.maze.rooms.len()
     ));
 
+    let mut movements = laby_data.next_directions.to_display();
+    if laby_data
+        .maze
+        .is_open(laby_data.position, laby_data.facing.back())
+    {
+        if !movements.is_empty() {
+            movements.push_str(", ");
+        }
+        movements.push_str("Back");
+    }
+    res.push_str(&format!("Available movements: [{}]\n\n", movements));
+
     // Description
     res.push_str("-------------------[Description]------------------\n");
     res.push_str(&format!("{}\n", laby_data.description));
@@ -176,11 +423,12 @@ fn turn_display(laby_data: &ResMut<LabyrinthData>) -> String {
     res
 }
 
-fn enemy_display(laby_data: &ResMut<LabyrinthData>) -> String {
+fn enemy_display(laby_data: &ResMut<LabyrinthData>, player: &ResMut<PlayerStats>) -> String {
     let mut res = String::from("----------------------[View]----------------------\n");
     res.push_str(laby_data.enemy.get_ascii_art());
     res.push('\n');
 
+    res.push_str(&format!("Enemy: {}\n", laby_data.enemy.name));
     res.push_str(&format!(
         "Health: {}\n",
         display_bar(
@@ -189,7 +437,25 @@ fn enemy_display(laby_data: &ResMut<LabyrinthData>) -> String {
             laby_data.enemy.max_health.into()
         )
     ));
-    res.push_str("Available actions: [prepare, protect, attack, skip]\n");
+    if !laby_data.enemy.effects.effects.is_empty() {
+        res.push_str(&format!(
+            "Status: {}\n",
+            laby_data.enemy.effects.to_display()
+        ));
+    }
+    res.push_str(&format!("{}\n", laby_data.enemy.get_intention_display()));
+    let mut actions = vec![
+        "prepare",
+        "protect",
+        "attack",
+        "use <potion>",
+        "equip <sword>",
+    ];
+    if let Some(class) = &player.class {
+        actions.push(&class.ability.command);
+    }
+    actions.push("skip");
+    res.push_str(&format!("Available actions: [{}]\n", actions.join(", ")));
     res.push('\n');
 
     // Description
@@ -206,25 +472,76 @@ fn item_display(laby_data: &ResMut<LabyrinthData>) -> String {
 
     // Description
     res.push_str("-------------------[Description]------------------\n");
-    res.push_str(
-        "
-Loot!
-Enter 'loot' to get its content
-or you can type 'skip' to go to the next room.\n\n",
-    );
+    if laby_data.item_type != ItemType::Chest {
+        res.push_str(&format!(
+            "\nYou found a {}!\n",
+            laby_data.item_type.to_display_str()
+        ));
+        res.push_str("Enter 'loot' to put it in your inventory\n");
+    } else if laby_data.is_chest_locked {
+        res.push_str("\nLoot! But the chest is locked...\n");
+        res.push_str("Enter 'loot' to open it with a key\n");
+    } else {
+        res.push_str("\nLoot!\n");
+        res.push_str("Enter 'loot' to get its content\n");
+    }
+    res.push_str("or you can type 'skip' to go to the next room.\n\n");
 
     res
 }
 
-fn npc_display(laby_data: &ResMut<LabyrinthData>) -> String {
+fn npc_display(
+    laby_data: &ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    player: &ResMut<PlayerStats>,
+    vuln_res: &ResMut<VulnerabilityResource>,
+) -> String {
     let mut res = String::from("----------------------[View]----------------------\n");
     res.push_str(art::KNIGHT);
     res.push('\n');
 
     res.push_str(&format!("Username: {}\n", laby_data.npc.username));
+    res.push_str(&format!(
+        "Relationship: {}\n",
+        vuln_res.relationships.to_display(&laby_data.npc.username)
+    ));
 
     // Description
     res.push_str("---------------------[Talking]--------------------\n");
+
+    // the conversation going on
+    if let Some(state) = laby_data.dialogue {
+        let node = &laby_res.dialogues[state.dialogue].nodes[state.node];
+        res.push_str(&format!(
+            "{}: \"{}\"\n\n",
+            laby_data.npc.username,
+            node.get_line(&laby_data.npc.username)
+        ));
+        for (index, choice) in node.choices.iter().enumerate() {
+            res.push_str(&format!(
+                "{}. {}",
+                index + 1,
+                choice.get_text(&laby_data.npc.username)
+            ));
+            if let Some(condition) = &choice.condition {
+                if !condition.is_met(player) {
+                    res.push_str(&format!(" ({})", condition.to_display()));
+                }
+            }
+            res.push('\n');
+        }
+        res.push_str("\nType the number of your answer, or 'insult' to end it rudely\n");
+
+        return res;
+    }
+
+    if let Some(quest) = &laby_data.offered_quest {
+        res.push_str(&format!(
+            "{}: \"{}\"\n(type 'accept' to take the quest)\n",
+            laby_data.npc.username, quest.request
+        ));
+    }
+
     res.push_str(
         "
         You encounter another player.\n
@@ -233,7 +550,94 @@ fn npc_display(laby_data: &ResMut<LabyrinthData>) -> String {
         \n\n",
     );
 
-    res.push_str("Type 'talk' to speak with him/her 'insult' to .. insult the npc\nor 'skip' to go to the next room\n");
+    res.push_str("Type 'talk' to speak with him/her 'insult' to .. insult the npc,\n'give <item>' to offer a gift, 'apologize' if you upset them\nor 'skip' to go to the next room\n");
+
+    res
+}
+
+fn riddle_display(
+    laby_data: &ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+) -> String {
+    let riddle = &laby_res.riddles[laby_data.puzzle];
+    let mut res = String::from("----------------------[View]----------------------\n");
+    res.push_str(art::RIDDLE_DOOR);
+    res.push('\n');
+
+    res.push_str("---------------------[Riddle]---------------------\n");
+    res.push_str("A voice comes out of the door:\n");
+    res.push_str(&format!("\"{}\"\n\n", riddle.question));
+    res.push_str("Type 'answer <your answer>', you only get one try\n");
+    res.push_str("or 'skip' to force your way through.\n\n");
+
+    res
+}
+
+fn trap_display(
+    laby_data: &ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+) -> String {
+    let trap = &laby_res.traps[laby_data.puzzle];
+    let mut res = String::from("----------------------[View]----------------------\n");
+    res.push_str(art::TRAP);
+    res.push('\n');
+
+    // Description
+    res.push_str("-------------------[Description]------------------\n");
+    res.push_str(&format!("{}\n", trap.description));
+    if laby_data.is_trap_inspected {
+        res.push_str(&format!("{}\n", trap.hint));
+    }
+    res.push_str("\nType 'inspect' to take a closer look, 'disarm' to get rid of it\n");
+    res.push_str("or 'skip' to walk across and hope for the best.\n\n");
+
+    res
+}
+
+fn gate_display(
+    laby_data: &ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    player: &ResMut<PlayerStats>,
+) -> String {
+    let gate = &laby_res.gates[laby_data.puzzle];
+    let mut res = String::from("----------------------[View]----------------------\n");
+    res.push_str(art::GATE);
+    res.push('\n');
+
+    // Description
+    res.push_str("-------------------[Description]------------------\n");
+    res.push_str(&format!("{}\n\n", gate.description));
+    if player.count_items(ItemType::Key) > 0 {
+        res.push_str("Type 'open' to unlock it with a key, ");
+    } else {
+        res.push_str("You have no key... Type ");
+    }
+    res.push_str("'force' to try breaking it\nor 'go back' to turn around.\n\n");
+
+    res
+}
+
+fn merchant_display(laby_data: &ResMut<LabyrinthData>) -> String {
+    let mut res = String::from("----------------------[View]----------------------\n");
+    res.push_str(art::MERCHANT);
+    res.push('\n');
+
+    res.push_str("---------------------[Merchant]-------------------\n");
+    res.push_str("\"Psst... Want to buy something?\"\n\n");
+    if laby_data.merchant_stock.is_empty() {
+        res.push_str("Sold out!\n");
+    }
+    for (index, (item, quantity)) in laby_data.merchant_stock.iter().enumerate() {
+        res.push_str(&format!(
+            "{}. {} - {} gold (x{})\n",
+            index + 1,
+            item.to_display(),
+            item.price,
+            quantity
+        ));
+    }
+
+    res.push_str("\nType 'buy <item>' or 'sell <item>' to trade with him\nor 'skip' to go to the next room\n");
 
     res
 }
@@ -242,124 +646,977 @@ fn player_infos(player: &ResMut<PlayerStats>) -> String {
     let mut res = String::from("------------------[Player Stats]------------------\n\n");
 
     res.push_str(&format!(
-        "Level: {} | Exp: {} | Gold: 0\n",
-        player.level, player.exp,
+        "Level: {} | Exp: {} | Gold: {}\n",
+        player.level, player.exp, player.gold,
     ));
+    if let Some(class) = &player.class {
+        res.push_str(&format!(
+            "Class: {} | Ability '{}': {}\n",
+            class.name,
+            class.ability.command,
+            match player.ability_cooldown {
+                0 => "ready".to_string(),
+                turns => format!("ready in {} turns", turns),
+            }
+        ));
+    }
     res.push_str(&format!(
         "Health: {}\n",
         utils::display_bar(20, player.health as f64, player.max_health as f64)
     ));
+    res.push_str(&format!(
+        "Weapon: {} | Keys: {} | Potions: {}\n",
+        match &player.weapon {
+            Some(weapon) => weapon.to_display(),
+            None => "Fists".to_string(),
+        },
+        player.count_items(ItemType::Key),
+        player.count_items(ItemType::Potion),
+    ));
+    if player.skill_points > 0 {
+        res.push_str(&format!(
+            "Skill points: {} (type 'levelup' to spend them)\n",
+            player.skill_points
+        ));
+    }
+    if !player.effects.effects.is_empty() {
+        res.push_str(&format!("Status: {}\n", player.effects.to_display()));
+    }
+    if !player.party.is_empty() {
+        let party: Vec<String> = player
+            .party
+            .iter()
+            .map(|companion| companion.to_display())
+            .collect();
+        res.push_str(&format!("Party: {}\n", party.join(", ")));
+    }
+
+    res
+}
+
+/// Turns the exp into levels, returns true on a new level
+fn level_up(player: &mut ResMut<PlayerStats>, laby_res: &Res<LabyrinthResourceFile>) -> bool {
+    let mut has_leveled_up = false;
+
+    loop {
+        let exp_to_level_up = laby_res.rules.exp_curve.get_exp_to_level_up(player.level);
+        if player.exp < exp_to_level_up {
+            break;
+        }
+        player.exp -= exp_to_level_up;
+        player.level += 1;
+        player.skill_points += laby_res.rules.skill_points_per_level;
+        has_leveled_up = true;
+
+        // each class grows in its own way
+        let (max_health, health, damages) = {
+            let bonus = player
+                .class
+                .as_ref()
+                .and_then(|class| class.get_level_bonus(player.level))
+                .unwrap_or(&laby_res.rules.player_level_bonus);
+            (bonus.max_health, bonus.health, bonus.damages)
+        };
+        player.max_health += max_health;
+        player.damages += damages;
+        player.health += health;
+        player.health = player.health.min(player.max_health);
+    }
+
+    has_leveled_up
+}
+
+fn display_level_up(laby_res: &Res<LabyrinthResourceFile>, player: &ResMut<PlayerStats>) -> String {
+    let mut res = String::from("---------------------[Level Up]-------------------\n\n");
+    res.push_str(&format!(
+        "Level: {} | Skill points: {}\n",
+        player.level, player.skill_points
+    ));
+    res.push_str(&format!(
+        "Health: {}/{} | Damages: {}\n\n",
+        player.health, player.max_health, player.damages
+    ));
+
+    res.push_str("Stats (1 pt each):\n");
+    for upgrade in laby_res.upgrades.iter() {
+        res.push_str(&format!("- {}\n", upgrade.to_display()));
+    }
+    res.push_str("\nSkills:\n");
+    for skill in laby_res.skills.iter() {
+        if player.has_skill(&skill.name) {
+            res.push_str(&format!("- {} (learned)\n", skill.name));
+        } else {
+            res.push_str(&format!("- {}\n", skill.to_display()));
+        }
+    }
+
+    res.push_str("\nType 'spend <stat>' or 'learn <skill>',\n");
+    res.push_str("or 'continue' to keep your points for later ('levelup').\n");
+
+    res
+}
+
+// every item of the loot table has its own chance to drop
+fn roll_enemy_loot(
+    enemy: &Enemy,
+    laby_res: &Res<LabyrinthResourceFile>,
+    player: &mut ResMut<PlayerStats>,
+    laby_rng: &mut ResMut<LabyrinthRng>,
+    run_stats: &mut RunStats,
+) -> Vec<String> {
+    let mut res = Vec::new();
+
+    for entry in enemy.loot.iter() {
+        if !laby_rng
+            .for_enemy(enemy)
+            .gen_bool(entry.chance.clamp(0.0, 1.0))
+        {
+            continue;
+        }
+        let item = match Item::find_by_name(&laby_res.items, &entry.item) {
+            Some(item) => item.clone(),
+            None => continue,
+        };
+
+        let name = item.to_display();
+        res.push(match player.add_item(item) {
+            Ok(()) => {
+                run_stats.items_found.push(entry.item.clone());
+                format!("The {} dropped a {}!", enemy.name, name)
+            }
+            Err(_) => format!(
+                "The {} dropped a {} but your bag is full...",
+                enemy.name, name
+            ),
+        });
+    }
 
     res
 }
 
-pub fn new_turn(
+/// Starts the run in a freshly generated maze, the seed comes from
+/// the `--seed` option or is picked at random
+pub fn start_exploring(
     laby_data: &mut ResMut<LabyrinthData>,
-    laby_res: &ResMut<LabyrinthResourceFile>,
+    laby_res: &Res<LabyrinthResourceFile>,
     player: &mut ResMut<PlayerStats>,
     npc_res: &Res<NPCsResource>,
+    laby_rng: &mut ResMut<LabyrinthRng>,
+    cg_data: &ResMut<ConsoleGamesData>,
 ) {
-    // first wait a bit
-    thread::sleep(time::Duration::from_secs_f32(
-        rand::thread_rng().gen_range(0.2..=0.7),
+    laby_data.game_state = GameState::Exploring;
+
+    // the tutorial can be read again in the middle of a run
+    if laby_data.steps_number > 0 {
+        return;
+    }
+
+    // the class is chosen right after the tutorial
+    if player.class.is_none() && !laby_res.classes.is_empty() {
+        laby_data.game_state = GameState::CharacterCreation;
+        return;
+    }
+
+    // the options were already checked when the game was launched
+    let (seed, mode) = match RunOptions::from_args(&cg_data.launch_args) {
+        Ok(options) => (options.seed, options.mode),
+        Err(_) => (None, RunMode::Story),
+    };
+    laby_rng.reseed(seed.unwrap_or_else(|| rand::thread_rng().gen()));
+    laby_data.mode = mode;
+
+    let rules = &laby_res.rules;
+    laby_data.maze = Maze::generate(
+        rules.maze_width,
+        rules.maze_height,
+        rules.treasure_rooms,
+        &mut laby_rng.rng,
+    );
+    laby_data.position = laby_data.maze.start;
+    laby_data.facing = Cardinal::North;
+    laby_data.met_rivals.clear();
+    laby_data.quests.clear();
+    laby_data.journal.clear();
+    laby_data.run_stats.started = Some(Instant::now());
+
+    // the log starts once the journal of the last run is gone
+    if let Some(class) = &player.class {
+        let msg = format!("Chose the {} class", class.name);
+        laby_data.add_log(&msg);
+    }
+    let msg = match mode {
+        RunMode::Story => "Entered the labyrinth".to_string(),
+        mode => format!("Entered the labyrinth ({})", mode.to_display_str()),
+    };
+    laby_data.add_log(&msg);
+
+    // nobody waits at the entrance
+    new_turn(laby_data, laby_res, player, npc_res, laby_rng, &[]);
+
+    laby_data.status_message = display_seed(laby_rng);
+    if let Some(floor) = laby_res.floors.first() {
+        let msg = format!("\n\n-=[{}]=-\n{}", floor.name, floor.intro);
+        laby_data.status_message.push_str(&msg);
+    }
+}
+
+/// Moves the player to the next room if there is a path,
+/// a key from the inventory opens the locked doors
+pub fn move_player(
+    laby_data: &mut ResMut<LabyrinthData>,
+    player: &mut ResMut<PlayerStats>,
+    movement: Movement,
+) -> Result<(), String> {
+    let direction = laby_data.facing.turn(&movement);
+    if !laby_data.maze.is_open(laby_data.position, direction) {
+        return Err("There is no path in this direction...".to_string());
+    }
+
+    if laby_data.maze.is_locked(laby_data.position, direction) {
+        if player.take_item(ItemType::Key).is_none() {
+            return Err("This door is locked... You need a key to open it".to_string());
+        }
+        let position = laby_data.position;
+        laby_data.maze.set_locked(position, direction, false);
+        laby_data.status_message = "You unlock the door with your key".to_string();
+    }
+
+    laby_data.position = Maze::neighbor(laby_data.position, direction);
+    laby_data.facing = direction;
+
+    // the next room is entered once the walk is over
+    laby_data.walking = Some(Timer::from_seconds(
+        rand::thread_rng().gen_range(WALKING_TIME),
+        false,
     ));
+    laby_data.has_shown_turn_infos = true;
 
-    laby_data.steps_number += 1;
+    Ok(())
+}
 
-    player.health += 1.0;
-    player.health = player.health.min(player.max_health);
+/// Counts the in-game time of the run, the clock of the apartment
+/// keeps going while the player is in the labyrinth, until the run is over
+pub fn clock_system(
+    time: Res<Time>,
+    day_cycle: Res<DayCycleResource>,
+    mut laby_data: ResMut<LabyrinthData>,
+) {
+    if !laby_data.run_stats.is_running() {
+        return;
+    }
+    laby_data.run_stats.game_time += time.delta_seconds() / day_cycle.day_length * 24.0 * 60.0;
+}
 
-    if laby_data.steps_number == NUMBER_OF_TURN_TO_WIN {
-        laby_data.room_type = RoomType::Enemy;
-        laby_data.enemy = Enemy {
-            damages: 2.0,
-            description: "
-This is the end.
-You encounter the legendary gamer xxXDarkKevin420Xxx. No friendship here, only battle!"
-                .to_string(),
-            exp: 100,
-            health: 25.0,
-            max_health: 25.0,
-            kind: EnemyType::Boss,
-        };
+/// Walks to the next room without freezing the rest of the game
+pub fn walking_system(
+    time: Res<Time>,
+    mut laby_data: ResMut<LabyrinthData>,
+    laby_res: Res<LabyrinthResourceFile>,
+    mut player: ResMut<PlayerStats>,
+    npc_res: Res<NPCsResource>,
+    mut laby_rng: ResMut<LabyrinthRng>,
+    mut console_data: ResMut<ConsoleData>,
+    vuln_res: Res<VulnerabilityResource>,
+) {
+    let timer = match laby_data.walking.as_mut() {
+        Some(timer) => timer,
+        None => return,
+    };
+
+    timer.tick(time.delta());
+    if !timer.finished() {
+        // the dots are animated in place
+        let dots = (timer.elapsed_secs() / WALKING_DOTS_TIME) as usize % 3 + 1;
+        let msg = format!("Walking{}", ".".repeat(dots));
+        match console_data.messages.last_mut() {
+            Some(last) if last.starts_with("Walking") => *last = msg,
+            _ => console_data.messages.push(msg),
+        }
         return;
     }
 
-    // if we were in tutorial we go in corridor
-    if laby_data.steps_number <= 1 {
-        laby_data.room_type = RoomType::Corridor;
+    laby_data.walking = None;
+    new_turn(
+        &mut laby_data,
+        &laby_res,
+        &mut player,
+        &npc_res,
+        &mut laby_rng,
+        &vuln_res.relationships.get_hostiles(),
+    );
+    laby_data.has_shown_turn_infos = false;
+}
+
+fn claim_quest_rewards(
+    laby_data: &mut ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    player: &mut ResMut<PlayerStats>,
+    vuln_res: &mut ResMut<VulnerabilityResource>,
+) -> Vec<String> {
+    let mut res = Vec::new();
+
+    let completed: Vec<String> = laby_data
+        .quests
+        .iter()
+        .filter(|quest| quest.is_done)
+        .map(|quest| quest.giver.clone())
+        .collect();
+    for giver in completed {
+        laby_data
+            .journal
+            .on_npc(&giver, "thanked you for your help");
+        laby_data.add_log(&format!("Completed the quest of {}", giver));
+    }
+
+    let mut items_found = Vec::new();
+    for quest in laby_data.quests.iter().filter(|quest| quest.is_done) {
+        let reward = &quest.reward;
+        player.exp += reward.exp;
+        player.gold += reward.gold;
+        res.push(format!(
+            "{} rewards you with {} Exp and {} gold",
+            quest.giver, reward.exp, reward.gold
+        ));
+
+        if let Some(item) = reward
+            .item
+            .as_ref()
+            .and_then(|name| Item::find_by_name(&laby_res.items, name))
+        {
+            match player.add_item(item.clone()) {
+                Ok(()) => {
+                    items_found.push(item.name.clone());
+                    res.push(format!("{} also gives you a {}", quest.giver, item.name));
+                }
+                Err(msg) => res.push(msg),
+            }
+        }
+
+        if reward.friendship != 0 {
+            vuln_res
+                .relationships
+                .change(&quest.giver, reward.friendship);
+            res.push(format!(
+                "{}: {}",
+                quest.giver,
+                vuln_res.relationships.to_display(&quest.giver)
+            ));
+        }
+    }
+    laby_data.quests.retain(|quest| !quest.is_done);
+    laby_data.run_stats.items_found.extend(items_found);
+
+    res
+}
+
+/// Once the room is dealt with, only the corridor remains
+pub fn clear_room(
+    laby_data: &mut ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    laby_rng: &mut ResMut<LabyrinthRng>,
+) {
+    // the room of a beaten rival is described with the stream of the rivals
+    let rng = if laby_data.room_type == RoomType::Enemy {
+        laby_rng.for_enemy(&laby_data.enemy)
     } else {
-        // 10 rooms =
-        // 4 corridor, 1 item, 3 enemy, 2 npc
-        let rooms_possibilites = [
-            RoomType::Corridor,
-            RoomType::Corridor,
-            RoomType::Corridor,
-            RoomType::Corridor,
-            RoomType::Item,
-            RoomType::Enemy,
-            RoomType::Enemy,
-            RoomType::Enemy,
-            RoomType::Npc,
-            RoomType::Npc,
-        ];
-
-        let mut new_room: RoomType;
-        loop {
-            new_room = *rooms_possibilites.choose(&mut rand::thread_rng()).unwrap();
-
-            // if we don't have the same room
-            if new_room != laby_data.room_type {
-                break;
+        &mut laby_rng.rng
+    };
+
+    if laby_data.room_type != RoomType::Corridor {
+        laby_data.run_stats.rooms_cleared += 1;
+    }
+    laby_data.room_type = RoomType::Corridor;
+    laby_data.dialogue = None;
+    laby_data.offered_quest = None;
+    set_corridor(laby_data, laby_res, rng);
+}
+
+fn set_corridor(laby_data: &mut LabyrinthData, laby_res: &LabyrinthResourceFile, rng: &mut StdRng) {
+    laby_data.next_directions = laby_data
+        .maze
+        .get_directions(laby_data.position, laby_data.facing);
+
+    // each floor has its own atmosphere
+    let descriptions = match laby_res.get_floor(laby_data.floor, laby_data.mode) {
+        Some(floor) if !floor.descriptions.is_empty() => &floor.descriptions,
+        _ => &laby_res.descriptions,
+    };
+    let index = rng.gen_range(0..descriptions.len());
+    laby_data.description = descriptions.get(index).unwrap().clone();
+
+    if laby_data.next_directions == Directions::DeadEnd {
+        laby_data
+            .description
+            .push_str("\n\nDead end... You will have to go back.");
+    }
+
+    let position = laby_data.position;
+    let facing = laby_data.facing;
+    if [facing, facing.left(), facing.right()]
+        .iter()
+        .any(|dir| laby_data.maze.is_locked(position, *dir))
+    {
+        laby_data
+            .description
+            .push_str("\n\nThere is a locked door here.");
+    }
+
+    if position == laby_data.maze.exit && laby_data.is_stairs_open {
+        laby_data.description.push_str(
+            "\n\nStairs lead down into the dark. Type 'descend' to go to the next floor.",
+        );
+    }
+}
+
+// an enemy living on the floor of the player
+fn get_floor_enemy(
+    laby_data: &LabyrinthData,
+    laby_res: &LabyrinthResourceFile,
+    level: usize,
+    depth: usize,
+    rng: &mut StdRng,
+) -> Enemy {
+    let floor = laby_res.get_floor(laby_data.floor, laby_data.mode);
+    let pool = floor
+        .map(|floor| floor.enemies.as_slice())
+        .unwrap_or_default();
+
+    let mut enemy = Enemy::get_random_enemy(&laby_res.enemies, pool, depth, rng).clone();
+    let rules = &laby_res.rules;
+    enemy.scale(
+        &rules.enemy_level_bonus,
+        level,
+        &rules.enemy_depth_bonus,
+        depth,
+    );
+    if let Some(floor) = floor {
+        floor.strengthen(&mut enemy);
+    }
+    strengthen_endless(laby_data, laby_res, &mut enemy);
+    enemy
+}
+
+// the floors of the endless mode come back around, their enemies
+// are as strong as if each floor was a few more rooms deep
+fn strengthen_endless(
+    laby_data: &LabyrinthData,
+    laby_res: &LabyrinthResourceFile,
+    enemy: &mut Enemy,
+) {
+    if laby_data.mode != RunMode::Endless {
+        return;
+    }
+    let rules = &laby_res.rules;
+    let depth = laby_data.floor * rules.endless_floor_depth;
+    enemy.scale(&LevelBonus::default(), 1, &rules.enemy_depth_bonus, depth);
+}
+
+// the time at which the player left the floor
+fn push_split(laby_data: &mut ResMut<LabyrinthData>, laby_res: &Res<LabyrinthResourceFile>) {
+    let floor = laby_res
+        .get_floor(laby_data.floor, laby_data.mode)
+        .map(|floor| floor.name.clone())
+        .unwrap_or_else(|| format!("Floor {}", laby_data.floor + 1));
+    let split = Split {
+        floor,
+        time: laby_data.run_stats.get_duration(),
+        game_time: laby_data.run_stats.game_time,
+    };
+    laby_data.run_stats.splits.push(split);
+}
+
+/// Takes the stairs at the exit down to a brand new maze
+pub fn descend(
+    laby_data: &mut ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    player: &mut ResMut<PlayerStats>,
+    npc_res: &Res<NPCsResource>,
+    laby_rng: &mut ResMut<LabyrinthRng>,
+) {
+    push_split(laby_data, laby_res);
+    laby_data.floor += 1;
+    laby_data.is_stairs_open = false;
+    laby_data.closed_gates.clear();
+
+    let name = match laby_res.get_floor(laby_data.floor, laby_data.mode) {
+        Some(floor) => {
+            laby_data.status_message = format!("-=[{}]=-\n{}", floor.name, floor.intro);
+            floor.name.clone()
+        }
+        None => {
+            laby_data.status_message = "You go down the stairs...".to_string();
+            format!("Floor {}", laby_data.floor + 1)
+        }
+    };
+
+    // the rooms of the quests stayed upstairs
+    let abandoned: Vec<String> = laby_data
+        .quests
+        .iter()
+        .filter(|quest| !matches!(quest.goal, QuestGoal::Kill { .. }))
+        .map(|quest| quest.giver.clone())
+        .collect();
+    for giver in abandoned {
+        let msg = format!("\nYou left the quest of {} behind...", giver);
+        laby_data.status_message.push_str(&msg);
+    }
+    laby_data
+        .quests
+        .retain(|quest| matches!(quest.goal, QuestGoal::Kill { .. }));
+
+    let rules = &laby_res.rules;
+    laby_data.maze = Maze::generate(
+        rules.maze_width,
+        rules.maze_height,
+        rules.treasure_rooms,
+        &mut laby_rng.rng,
+    );
+    laby_data.position = laby_data.maze.start;
+    laby_data.facing = Cardinal::North;
+    laby_data.add_log(&format!("Went down to {}", name));
+    // nobody waits at the bottom of the stairs
+    new_turn(laby_data, laby_res, player, npc_res, laby_rng, &[]);
+}
+
+// enters the room the player is standing in
+fn new_turn(
+    laby_data: &mut ResMut<LabyrinthData>,
+    laby_res: &Res<LabyrinthResourceFile>,
+    player: &mut ResMut<PlayerStats>,
+    npc_res: &Res<NPCsResource>,
+    laby_rng: &mut ResMut<LabyrinthRng>,
+    hostiles: &[&str],
+) {
+    laby_data.steps_number += 1;
+
+    let position = laby_data.position;
+    let is_first_visit = !laby_data.maze.is_visited(position);
+    if let Some(room) = laby_data.maze.room_mut(position) {
+        room.visited = true;
+    }
+
+    // only a new room gives some rest, walking back and forth doesn't heal
+    if is_first_visit {
+        player.health += 1.0 + player.get_skill_bonus(SkillKind::Recovery);
+        player.health = player.health.min(player.max_health);
+
+        for companion in player.party.iter_mut() {
+            companion.heal(1.0);
+        }
+    }
+
+    // walking to the next room takes a turn
+    let health = player.health;
+    let max_health = player.max_health;
+    let stats = &mut **player;
+    let messages = stats.effects.tick("You", &mut stats.health, max_health);
+    for msg in messages {
+        laby_data.status_message.push_str(&format!("\n{}", msg));
+    }
+    laby_data.run_stats.damages_taken += (health - player.health).max(0.0);
+
+    // each floor of the endless mode goes a bit deeper
+    if laby_data.mode == RunMode::Endless {
+        let depth = laby_data.floor * laby_res.rules.endless_floor_depth
+            + laby_data.maze.get_depth(position);
+        laby_data.run_stats.depth_score = laby_data.run_stats.depth_score.max(depth);
+    }
+
+    // the quests about reaching a room or bringing something back
+    for index in 0..laby_data.quests.len() {
+        let item = match laby_data.quests[index].goal {
+            QuestGoal::Bring { item, .. } => Some(item),
+            _ => None,
+        };
+        let has_item = matches!(item, Some(kind) if player.count_items(kind) > 0);
+
+        if laby_data.quests[index].on_enter_room(position, has_item) {
+            if let Some(kind) = item {
+                player.take_item(kind);
+            }
+            let msg = format!("\nQuest completed for {}!", laby_data.quests[index].giver);
+            laby_data.status_message.push_str(&msg);
+        }
+    }
+
+    // the boss waits at the exit of the last floor,
+    // the guardians of the stairs at the exit of the others
+    if position == laby_data.maze.exit {
+        let guardian = if laby_res.is_last_floor(laby_data.floor, laby_data.mode) {
+            Some(&laby_res.rules.boss)
+        } else if laby_data.is_stairs_open {
+            None
+        } else {
+            laby_res
+                .get_floor(laby_data.floor, laby_data.mode)
+                .and_then(|floor| floor.guardian.as_ref())
+        };
+
+        match guardian {
+            Some(enemy) => {
+                let mut guardian = enemy.clone();
+                strengthen_endless(laby_data, laby_res, &mut guardian);
+                laby_data.room_type = RoomType::Enemy;
+                laby_data.enemy = guardian;
+                laby_data.enemy.choose_intention(&mut laby_rng.rng);
+                laby_data.journal.on_encounter(enemy);
+                laby_data.add_log(&format!("Faced {} at the exit", enemy.name));
+            }
+            None => {
+                laby_data.is_stairs_open = true;
+                clear_room(laby_data, laby_res, laby_rng);
             }
         }
-        laby_data.room_type = new_room;
+        return;
+    }
+
+    // a gate left closed is still there when coming back
+    let closed_gate = laby_data
+        .closed_gates
+        .iter()
+        .find(|(room, _)| *room == position)
+        .map(|(_, gate)| *gate);
+    if let Some(gate) = closed_gate {
+        laby_data.puzzle = gate;
+        laby_data.room_type = RoomType::Gate;
+        return;
+    }
+
+    // nothing new in the first room or in a room we already explored
+    if position == laby_data.maze.start || !is_first_visit {
+        clear_room(laby_data, laby_res, laby_rng);
+        if !is_first_visit {
+            laby_data.description =
+                format!("You've already been here...\n{}", laby_data.description);
+        }
+        return;
     }
 
+    // the reward for opening a locked door
+    if matches!(laby_data.maze.room(position), Some(room) if room.has_treasure) {
+        laby_data.room_type = RoomType::Item;
+        laby_data.item_type = ItemType::Chest;
+        laby_data.is_chest_locked = false;
+        return;
+    }
+
+    // the order of a HashMap changes between launches
+    let mut npcs: Vec<&NPCData> = npc_res.npcs.values().collect();
+    npcs.sort_by(|a, b| a.username.cmp(&b.username));
+
+    roll_room(laby_data, laby_res, player.level, &npcs, laby_rng, hostiles);
+}
+
+/// Rolls what waits in a new room. The room is always rolled with the
+/// rng of the run and a rival, rolled on its own stream, takes its place,
+/// so that a seed gives the same rooms whoever the player upset
+pub fn roll_room(
+    laby_data: &mut LabyrinthData,
+    laby_res: &LabyrinthResourceFile,
+    level: usize,
+    npcs: &[&NPCData],
+    laby_rng: &mut LabyrinthRng,
+    hostiles: &[&str],
+) {
+    let position = laby_data.position;
+    let depth = laby_data.maze.get_depth(position);
+
+    // the players the user upset come for revenge
+    let rivals: Vec<&str> = hostiles
+        .iter()
+        .copied()
+        .filter(|username| !laby_data.met_rivals.iter().any(|met| met == username))
+        .collect();
+    let rules = &laby_res.rules.rival;
+    let rival_rng = &mut laby_rng.rival_rng;
+    let rival = if rival_rng.gen_bool(rules.chance.clamp(0.0, 1.0)) && depth >= rules.min_depth {
+        rivals
+            .choose(rival_rng)
+            .map(|username| username.to_string())
+    } else {
+        None
+    };
+
+    let rng = &mut laby_rng.rng;
+
+    // we don't want the same room twice in a row, unless there is nothing else
+    let mut rooms_possibilities: Vec<&(RoomType, u32)> = laby_res
+        .rules
+        .room_weights
+        .iter()
+        .filter(|(room, weight)| *room != laby_data.last_rolled_room && *weight > 0)
+        .collect();
+    if rooms_possibilities.is_empty() {
+        rooms_possibilities = laby_res.rules.room_weights.iter().collect();
+    }
+
+    let new_room = rooms_possibilities
+        .choose_weighted(&mut *rng, |(_, weight)| *weight)
+        .map(|(room, _)| *room)
+        .unwrap_or(RoomType::Corridor);
+    laby_data.room_type = new_room;
+    laby_data.last_rolled_room = new_room;
+
     match laby_data.room_type {
         RoomType::Enemy => {
-            laby_data.enemy = Enemy::get_random_enemy(&laby_res.enemies).clone();
+            laby_data.enemy = get_floor_enemy(laby_data, laby_res, level, depth, rng);
         }
 
         RoomType::Item => {
-            laby_data.item_type = ItemType::get_random_item();
+            laby_data.item_type = ItemType::get_random_item(rng);
+            laby_data.is_chest_locked = true;
         }
 
         RoomType::Npc => {
-            // if there are npcs we haven't seen yet
-            if laby_data.seen_npcs.len() < npc_res.npcs.len() {
-                let mut npc: NPCData;
-                loop {
-                    npc = {
-                        let index = rand::thread_rng().gen_range(0..npc_res.npcs.values().count());
-
-                        npc_res.npcs.values().nth(index).unwrap().clone()
-                    };
-
-                    // if we found someone we didn't found before
-                    if !laby_data
-                        .seen_npcs
-                        .iter()
-                        .any(|username| username == &npc.username)
-                    {
-                        break;
-                    }
-                }
+            // one draw among the npcs we haven't seen yet
+            let unseen: Vec<&NPCData> = npcs
+                .iter()
+                .copied()
+                .filter(|npc| !laby_data.seen_npcs.contains(&npc.username))
+                .collect();
+            if let Some(npc) = unseen.choose(rng) {
+                let npc = (*npc).clone();
+                // some of them need a hand
+                let quest = if rng.gen_bool(laby_res.rules.quest_chance.clamp(0.0, 1.0)) {
+                    Quest::new_random(
+                        &laby_res.quests,
+                        &npc.username,
+                        &laby_data.maze,
+                        position,
+                        rng,
+                    )
+                } else {
+                    None
+                };
+
+                // still seen when a rival scares them away, the next rolls stay the same
                 laby_data.seen_npcs.push(npc.username.clone());
+                if rival.is_none() {
+                    laby_data
+                        .journal
+                        .on_npc(&npc.username, "was met in the labyrinth");
+                    laby_data.offered_quest = quest;
+                }
                 laby_data.npc = npc;
             }
             // else we show a basic enemy
             else {
                 laby_data.room_type = RoomType::Enemy;
-                laby_data.enemy = Enemy::get_random_enemy(&laby_res.enemies).clone();
+                laby_data.enemy = get_floor_enemy(laby_data, laby_res, level, depth, rng);
             }
         }
 
-        RoomType::Corridor => {
-            laby_data.next_directions = Directions::get_random_direction();
+        RoomType::Merchant => {
+            laby_data.merchant_stock = laby_res
+                .merchant_stock
+                .iter()
+                .filter_map(|stock| {
+                    Item::find_by_name(&laby_res.items, &stock.item)
+                        .map(|item| (item.clone(), stock.quantity))
+                })
+                .collect();
+        }
 
-            let index = rand::thread_rng().gen_range(0..laby_res.descriptions.len());
-            laby_data.description = laby_res.descriptions.get(index).unwrap().clone();
+        RoomType::Riddle | RoomType::Trap | RoomType::Gate => {
+            let count = match laby_data.room_type {
+                RoomType::Riddle => laby_res.riddles.len(),
+                RoomType::Trap => laby_res.traps.len(),
+                _ => laby_res.gates.len(),
+            };
+            if count == 0 {
+                laby_data.room_type = RoomType::Corridor;
+                set_corridor(laby_data, laby_res, rng);
+            } else {
+                laby_data.puzzle = rng.gen_range(0..count);
+                laby_data.is_trap_inspected = false;
+            }
         }
+
+        RoomType::Corridor => set_corridor(laby_data, laby_res, rng),
+    }
+
+    if laby_data.room_type == RoomType::Enemy {
+        laby_data.enemy.choose_intention(rng);
+    }
+
+    if let Some(username) = rival {
+        laby_data.room_type = RoomType::Enemy;
+        laby_data.enemy = Enemy::new_rival(&username, rules, level, depth);
+        laby_data.enemy.choose_intention(&mut laby_rng.rival_rng);
+        laby_data.add_log(&format!("{} came for revenge", username));
+        laby_data.met_rivals.push(username);
+    }
+
+    if laby_data.room_type == RoomType::Enemy {
+        let enemy = laby_data.enemy.clone();
+        laby_data.journal.on_encounter(&enemy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ron::de::from_bytes;
+
+    use super::*;
+
+    fn load_resources() -> LabyrinthResourceFile {
+        from_bytes(include_bytes!("../../../data/labyrinth_data.ron")).unwrap()
+    }
+
+    fn new_npcs(usernames: &[&str]) -> Vec<NPCData> {
+        usernames
+            .iter()
+            .map(|username| NPCData {
+                sprite_id: 0,
+                username: username.to_string(),
+            })
+            .collect()
+    }
+
+    // what each room of a freshly generated maze holds, in the order they were rolled
+    fn roll_rooms(
+        laby_data: &mut LabyrinthData,
+        laby_res: &LabyrinthResourceFile,
+        npcs: &[NPCData],
+        hostiles: &[&str],
+        seed: u64,
+    ) -> Vec<String> {
+        let npcs: Vec<&NPCData> = npcs.iter().collect();
+        let mut laby_rng = LabyrinthRng::default();
+        laby_rng.reseed(seed);
+        let rules = &laby_res.rules;
+        laby_data.maze = Maze::generate(
+            rules.maze_width,
+            rules.maze_height,
+            rules.treasure_rooms,
+            &mut laby_rng.rng,
+        );
+
+        let mut rooms = Vec::new();
+        for y in 0..laby_data.maze.height {
+            for x in 0..laby_data.maze.width {
+                laby_data.position = (x, y);
+                roll_room(laby_data, laby_res, 1, &npcs, &mut laby_rng, hostiles);
+                rooms.push(match laby_data.room_type {
+                    RoomType::Enemy => format!("Enemy {}", laby_data.enemy.name),
+                    RoomType::Riddle | RoomType::Trap | RoomType::Gate => {
+                        format!("{:?} {}", laby_data.room_type, laby_data.puzzle)
+                    }
+                    room => format!("{:?}", room),
+                });
+            }
+        }
+        rooms
+    }
+
+    #[test]
+    fn same_seed_gives_same_rooms() {
+        let laby_res = load_resources();
+        let npcs = new_npcs(&["alice", "bob", "carol"]);
+
+        let first = roll_rooms(&mut LabyrinthData::default(), &laby_res, &npcs, &[], 42);
+        let second = roll_rooms(&mut LabyrinthData::default(), &laby_res, &npcs, &[], 42);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn reset_replays_the_same_run() {
+        let laby_res = load_resources();
+        let npcs = new_npcs(&["alice", "bob", "carol"]);
+
+        let mut laby_data = LabyrinthData::default();
+        let first = roll_rooms(&mut laby_data, &laby_res, &npcs, &[], 7);
+        laby_data.reset();
+        let second = roll_rooms(&mut laby_data, &laby_res, &npcs, &[], 7);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn usernames_and_met_rivals_dont_change_the_rolls() {
+        let laby_res = load_resources();
+
+        let first = roll_rooms(
+            &mut LabyrinthData::default(),
+            &laby_res,
+            &new_npcs(&["alice", "bob", "carol"]),
+            &[],
+            3,
+        );
+
+        let mut laby_data = LabyrinthData::default();
+        laby_data.met_rivals.push("mallory".to_string());
+        let second = roll_rooms(
+            &mut laby_data,
+            &laby_res,
+            &new_npcs(&["zed", "yann", "xavier"]),
+            &["mallory"],
+            3,
+        );
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn unmet_rivals_only_take_the_place_of_rooms() {
+        let mut laby_res = load_resources();
+        laby_res.rules.rival.chance = 0.3;
+        laby_res.rules.rival.min_depth = 0;
+        let npcs = new_npcs(&["alice", "bob", "carol"]);
+
+        let first = roll_rooms(&mut LabyrinthData::default(), &laby_res, &npcs, &[], 5);
+        let second = roll_rooms(
+            &mut LabyrinthData::default(),
+            &laby_res,
+            &npcs,
+            &["mallory", "trent"],
+            5,
+        );
+
+        let rival_rooms: Vec<usize> = (0..first.len())
+            .filter(|&i| first[i] != second[i])
+            .collect();
+        assert_eq!(rival_rooms.len(), 2);
+        for i in rival_rooms {
+            assert!(second[i] == "Enemy mallory" || second[i] == "Enemy trent");
+        }
+    }
+
+    #[test]
+    fn rules_of_the_data_file_are_valid() {
+        assert_eq!(load_resources().rules.validate(), Ok(()));
+    }
+
+    #[test]
+    fn empty_maze_is_rejected() {
+        let mut laby_res = load_resources();
+        laby_res.rules.maze_width = 0;
+        assert!(laby_res.rules.validate().is_err());
+    }
+
+    #[test]
+    fn every_art_file_is_embedded() {
+        let mut laby_res = load_resources();
+        laby_res.load_art();
+        for enemy in laby_res.enemies.iter() {
+            assert!(!enemy.art.is_empty(), "no art for {:?}", enemy.kind);
+        }
+        assert!(!laby_res.rules.boss.art.is_empty());
+    }
+
+    #[test]
+    fn clock_stops_when_the_run_is_over() {
+        let mut stats = RunStats {
+            started: Some(Instant::now() - Duration::from_secs(5)),
+            ..Default::default()
+        };
+        assert!(stats.is_running());
+
+        stats.stop_clock();
+        let duration = stats.get_duration();
+        assert!(!stats.is_running());
+        assert!(duration >= Duration::from_secs(5));
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(stats.get_duration(), duration);
     }
 }
@@ -1,11 +1,24 @@
-use rand::Rng;
+use std::ops::RangeInclusive;
 
-use super::art;
+use rand::{prelude::SliceRandom, Rng};
+use serde::Deserialize;
 
+use super::{
+    art,
+    effects::{EffectType, StatusEffect},
+};
+
+// how many items the player can carry
+pub const INVENTORY_SIZE: usize = 6;
+// gold found in a chest along with the item
+pub const CHEST_GOLD: RangeInclusive<usize> = 5..=15;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
 pub enum ItemType {
     Chest,
     Key,
     Sword,
+    Potion,
 }
 
 impl ItemType {
@@ -14,23 +27,97 @@ impl ItemType {
             ItemType::Chest => art::CHEST,
             ItemType::Key => art::KEY,
             ItemType::Sword => art::SWORD,
+            ItemType::Potion => art::POTION,
         }
     }
 
-    pub fn _to_display_str(&self) -> &str {
+    pub fn to_display_str(self) -> &'static str {
         match self {
             ItemType::Chest => "chest",
             ItemType::Key => "key",
             ItemType::Sword => "sword",
+            ItemType::Potion => "potion",
         }
     }
 
-    pub fn get_random_item() -> ItemType {
-        let index = rand::thread_rng().gen_range(0..2);
+    pub fn get_random_item(rng: &mut impl Rng) -> ItemType {
+        let index = rng.gen_range(0..4);
         match index {
             0 => ItemType::Chest,
             1 => ItemType::Key,
-            _ => ItemType::Sword,
+            2 => ItemType::Sword,
+            _ => ItemType::Potion,
+        }
+    }
+}
+
+/// Something the player can carry, defined in the labyrinth data file
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Item {
+    pub kind: ItemType,
+    pub name: String,
+    // bonus damages when equipped, for the swords
+    #[serde(default)]
+    pub damages: f32,
+    // health given back when used, for the potions
+    #[serde(default)]
+    pub heal: f32,
+    // what the merchant asks for it, he buys it back for half
+    #[serde(default)]
+    pub price: usize,
+    // given to the player by a potion, or to the enemy hit by a sword
+    #[serde(default)]
+    pub effect: Option<StatusEffect>,
+    // effects removed by a potion
+    #[serde(default)]
+    pub cures: Vec<EffectType>,
+}
+
+/// A line of the merchant stock table
+#[derive(Debug, Deserialize, Clone)]
+pub struct MerchantStock {
+    // name of an item of the items list
+    pub item: String,
+    pub quantity: usize,
+}
+
+impl Item {
+    pub fn get_random_of_kind<'a>(
+        items: &'a [Item],
+        kind: ItemType,
+        rng: &mut impl Rng,
+    ) -> Option<&'a Item> {
+        let candidates: Vec<&Item> = items.iter().filter(|item| item.kind == kind).collect();
+        candidates.choose(rng).copied()
+    }
+
+    pub fn find_by_name<'a>(items: &'a [Item], name: &str) -> Option<&'a Item> {
+        items.iter().find(|item| item.name == name)
+    }
+
+    pub fn get_sell_price(&self) -> usize {
+        self.price / 2
+    }
+
+    pub fn to_display(&self) -> String {
+        let mut stats = Vec::new();
+        if self.damages > 0.0 {
+            stats.push(format!("+{} dmg", self.damages));
+        }
+        if self.heal > 0.0 {
+            stats.push(format!("+{} HP", self.heal));
+        }
+        if let Some(effect) = &self.effect {
+            stats.push(effect.kind.to_display_str().to_lowercase());
+        }
+        for kind in self.cures.iter() {
+            stats.push(format!("cures {}", kind.to_display_str().to_lowercase()));
+        }
+
+        if stats.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} ({})", self.name, stats.join(", "))
         }
     }
 }
@@ -0,0 +1,108 @@
+use crate::npcs::Interaction;
+
+use super::enemies::{Enemy, EnemyType};
+
+/// What the player saw and did during the run
+#[derive(Debug, Default)]
+pub struct Journal {
+    // in the order they were met
+    pub bestiary: Vec<BestiaryEntry>,
+    pub npcs: Vec<NpcEntry>,
+    // the notable events of the run
+    pub log: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct BestiaryEntry {
+    pub kind: EnemyType,
+    pub name: String,
+    pub description: String,
+    pub art: String,
+    pub encounters: usize,
+    pub kills: usize,
+}
+
+#[derive(Debug)]
+pub struct NpcEntry {
+    pub username: String,
+    pub events: Vec<String>,
+}
+
+impl Journal {
+    pub fn clear(&mut self) {
+        self.bestiary.clear();
+        self.npcs.clear();
+        self.log.clear();
+    }
+
+    fn get_entry(&mut self, enemy: &Enemy) -> &mut BestiaryEntry {
+        match self
+            .bestiary
+            .iter()
+            .position(|entry| entry.kind == enemy.kind)
+        {
+            Some(index) => &mut self.bestiary[index],
+            None => {
+                self.bestiary.push(BestiaryEntry {
+                    kind: enemy.kind.clone(),
+                    name: enemy.name.clone(),
+                    description: enemy.description.clone(),
+                    art: enemy.get_ascii_art().to_string(),
+                    encounters: 0,
+                    kills: 0,
+                });
+                self.bestiary.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// The rivals are written down with the other NPCs
+    pub fn on_encounter(&mut self, enemy: &Enemy) {
+        match &enemy.rival {
+            Some(username) => self.on_npc(username, "came for revenge"),
+            None => self.get_entry(enemy).encounters += 1,
+        }
+    }
+
+    pub fn on_kill(&mut self, enemy: &Enemy) {
+        match &enemy.rival {
+            Some(username) => self.on_npc(username, "was beaten in a fight"),
+            None => self.get_entry(enemy).kills += 1,
+        }
+    }
+
+    pub fn on_npc(&mut self, username: &str, event: &str) {
+        match self
+            .npcs
+            .iter_mut()
+            .find(|entry| entry.username == username)
+        {
+            Some(entry) => entry.events.push(event.to_string()),
+            None => self.npcs.push(NpcEntry {
+                username: username.to_string(),
+                events: vec![event.to_string()],
+            }),
+        }
+    }
+
+    pub fn on_interaction(&mut self, username: &str, interaction: Interaction) {
+        let event = match interaction {
+            Interaction::Talk => "had a chat",
+            Interaction::Insult => "was insulted",
+            Interaction::Skip => "was ignored",
+            Interaction::Disappoint => "was disappointed",
+            Interaction::Gift => "received a gift",
+            Interaction::Apology => "accepted your apology",
+            Interaction::RivalBeaten => "was beaten in a fight",
+            Interaction::RivalWon => "beat you in a fight",
+            _ => return,
+        };
+        self.on_npc(username, event);
+    }
+
+    /// `room` is the number of steps since the start of the run
+    pub fn add_log(&mut self, floor: usize, room: usize, event: &str) {
+        self.log
+            .push(format!("[Floor {}, step {}] {}", floor + 1, room, event));
+    }
+}
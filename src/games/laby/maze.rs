@@ -0,0 +1,353 @@
+use std::collections::VecDeque;
+
+use rand::{prelude::SliceRandom, Rng};
+
+use super::data::{Directions, Movement};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cardinal {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Cardinal {
+    pub const ALL: [Cardinal; 4] = [
+        Cardinal::North,
+        Cardinal::East,
+        Cardinal::South,
+        Cardinal::West,
+    ];
+
+    fn index(&self) -> usize {
+        match self {
+            Cardinal::North => 0,
+            Cardinal::East => 1,
+            Cardinal::South => 2,
+            Cardinal::West => 3,
+        }
+    }
+
+    pub fn delta(&self) -> (i32, i32) {
+        match self {
+            Cardinal::North => (0, -1),
+            Cardinal::East => (1, 0),
+            Cardinal::South => (0, 1),
+            Cardinal::West => (-1, 0),
+        }
+    }
+
+    pub fn back(&self) -> Cardinal {
+        Cardinal::ALL[(self.index() + 2) % 4]
+    }
+
+    pub fn left(&self) -> Cardinal {
+        Cardinal::ALL[(self.index() + 3) % 4]
+    }
+
+    pub fn right(&self) -> Cardinal {
+        Cardinal::ALL[(self.index() + 1) % 4]
+    }
+
+    // where a movement leads when facing this direction
+    pub fn turn(&self, movement: &Movement) -> Cardinal {
+        match movement {
+            Movement::Forward => *self,
+            Movement::Left => self.left(),
+            Movement::Right => self.right(),
+            Movement::Back => self.back(),
+        }
+    }
+
+    fn to_arrow(self) -> char {
+        match self {
+            Cardinal::North => '^',
+            Cardinal::East => '>',
+            Cardinal::South => 'v',
+            Cardinal::West => '<',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MazeRoom {
+    // indexed like Cardinal::ALL
+    pub openings: [bool; 4],
+    pub locked: [bool; 4],
+    pub visited: bool,
+    pub has_treasure: bool,
+}
+
+/// A perfect maze: there is exactly one path between two rooms,
+/// so dead ends are everywhere and the player has to backtrack.
+/// It stays empty until a run starts.
+#[derive(Debug, Clone, Default)]
+pub struct Maze {
+    pub width: i32,
+    pub height: i32,
+    pub rooms: Vec<MazeRoom>,
+    pub start: (i32, i32),
+    // the boss waits here
+    pub exit: (i32, i32),
+}
+
+impl Maze {
+    /// `treasure_rooms` dead ends get closed by a locked door, with a chest inside
+    pub fn generate(width: i32, height: i32, treasure_rooms: usize, rng: &mut impl Rng) -> Maze {
+        let mut maze = Maze {
+            width,
+            height,
+            rooms: vec![MazeRoom::default(); (width * height) as usize],
+            start: (width / 2, height - 1),
+            exit: (width / 2, height - 1),
+        };
+
+        // randomized depth first search carving the corridors
+        let mut carved = vec![false; maze.rooms.len()];
+        let mut stack = vec![maze.start];
+        carved[maze.index(maze.start).unwrap()] = true;
+
+        while let Some(&position) = stack.last() {
+            let candidates: Vec<Cardinal> = Cardinal::ALL
+                .iter()
+                .copied()
+                .filter(|dir| {
+                    matches!(maze.index(Maze::neighbor(position, *dir)), Some(index) if !carved[index])
+                })
+                .collect();
+
+            match candidates.choose(rng) {
+                Some(dir) => {
+                    let next = Maze::neighbor(position, *dir);
+                    maze.open(position, *dir);
+                    carved[maze.index(next).unwrap()] = true;
+                    stack.push(next);
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+
+        maze.exit = maze.farthest_room(maze.start);
+        maze.lock_treasure_rooms(treasure_rooms, rng);
+        maze
+    }
+
+    fn lock_treasure_rooms(&mut self, treasure_rooms: usize, rng: &mut impl Rng) {
+        let mut dead_ends: Vec<(i32, i32)> = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|position| *position != self.start && *position != self.exit)
+            .filter(|position| {
+                Cardinal::ALL
+                    .iter()
+                    .filter(|dir| self.is_open(*position, **dir))
+                    .count()
+                    == 1
+            })
+            .collect();
+        dead_ends.shuffle(rng);
+
+        for position in dead_ends.into_iter().take(treasure_rooms) {
+            let dir = *Cardinal::ALL
+                .iter()
+                .find(|dir| self.is_open(position, **dir))
+                .unwrap();
+            self.set_locked(position, dir, true);
+            if let Some(room) = self.room_mut(position) {
+                room.has_treasure = true;
+            }
+        }
+    }
+
+    fn index(&self, (x, y): (i32, i32)) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        Some((y * self.width + x) as usize)
+    }
+
+    pub fn neighbor((x, y): (i32, i32), dir: Cardinal) -> (i32, i32) {
+        let (dx, dy) = dir.delta();
+        (x + dx, y + dy)
+    }
+
+    fn open(&mut self, position: (i32, i32), dir: Cardinal) {
+        let next = Maze::neighbor(position, dir);
+        if let (Some(a), Some(b)) = (self.index(position), self.index(next)) {
+            self.rooms[a].openings[dir.index()] = true;
+            self.rooms[b].openings[dir.back().index()] = true;
+        }
+    }
+
+    pub fn set_locked(&mut self, position: (i32, i32), dir: Cardinal, locked: bool) {
+        let next = Maze::neighbor(position, dir);
+        if let (Some(a), Some(b)) = (self.index(position), self.index(next)) {
+            self.rooms[a].locked[dir.index()] = locked;
+            self.rooms[b].locked[dir.back().index()] = locked;
+        }
+    }
+
+    pub fn room(&self, position: (i32, i32)) -> Option<&MazeRoom> {
+        self.index(position).map(|index| &self.rooms[index])
+    }
+
+    pub fn room_mut(&mut self, position: (i32, i32)) -> Option<&mut MazeRoom> {
+        self.index(position)
+            .map(move |index| &mut self.rooms[index])
+    }
+
+    pub fn is_open(&self, position: (i32, i32), dir: Cardinal) -> bool {
+        matches!(self.room(position), Some(room) if room.openings[dir.index()])
+    }
+
+    pub fn is_locked(&self, position: (i32, i32), dir: Cardinal) -> bool {
+        matches!(self.room(position), Some(room) if room.locked[dir.index()])
+    }
+
+    pub fn is_visited(&self, position: (i32, i32)) -> bool {
+        matches!(self.room(position), Some(room) if room.visited)
+    }
+
+    pub fn visited_count(&self) -> usize {
+        self.rooms.iter().filter(|room| room.visited).count()
+    }
+
+    // breadth first search, gives the number of rooms to walk through
+    // to reach each room and the last room reached, the farthest one
+    fn walk_from(&self, from: (i32, i32)) -> (Vec<usize>, (i32, i32)) {
+        let mut distances = vec![usize::MAX; self.rooms.len()];
+        let mut queue = VecDeque::from([from]);
+        let mut last = from;
+        distances[self.index(from).unwrap()] = 0;
+
+        while let Some(position) = queue.pop_front() {
+            last = position;
+            let distance = distances[self.index(position).unwrap()];
+
+            for dir in Cardinal::ALL {
+                let next = Maze::neighbor(position, dir);
+                if self.is_open(position, dir) && distances[self.index(next).unwrap()] == usize::MAX
+                {
+                    distances[self.index(next).unwrap()] = distance + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        (distances, last)
+    }
+
+    fn farthest_room(&self, from: (i32, i32)) -> (i32, i32) {
+        self.walk_from(from).1
+    }
+
+    /// How far from the entrance a room is, deeper rooms are more dangerous
+    pub fn get_depth(&self, position: (i32, i32)) -> usize {
+        match self.index(position) {
+            Some(index) => self.walk_from(self.start).0[index],
+            None => 0,
+        }
+    }
+
+    /// The view of the player: which ways are open in front of them
+    pub fn get_directions(&self, position: (i32, i32), facing: Cardinal) -> Directions {
+        let front = self.is_open(position, facing);
+        let left = self.is_open(position, facing.left());
+        let right = self.is_open(position, facing.right());
+
+        match (left, front, right) {
+            (true, true, true) => Directions::All,
+            (true, true, false) => Directions::LeftFront,
+            (true, false, false) => Directions::Left,
+            (false, true, true) => Directions::RightFront,
+            (false, false, true) => Directions::Right,
+            (false, true, false) => Directions::Front,
+            (true, false, true) => Directions::LeftRight,
+            (false, false, false) => Directions::DeadEnd,
+        }
+    }
+
+    fn display_room(&self, position: (i32, i32), player: (i32, i32), facing: Cardinal) -> String {
+        if position == player {
+            format!(" {} ", facing.to_arrow())
+        } else if !self.is_visited(position) {
+            "   ".to_string()
+        } else if position == self.exit {
+            " B ".to_string()
+        } else if position == self.start {
+            " S ".to_string()
+        } else {
+            " . ".to_string()
+        }
+    }
+
+    // walls are only known around the rooms the player has been in
+    fn is_known(&self, a: (i32, i32), b: (i32, i32)) -> bool {
+        self.is_visited(a) || self.is_visited(b)
+    }
+
+    // the rows nobody has seen yet are left out
+    fn push_line(res: &mut String, line: &str) {
+        if !line.trim().is_empty() {
+            res.push_str(line.trim_end());
+            res.push('\n');
+        }
+    }
+
+    /// Draws the part of the maze explored so far
+    pub fn display(&self, player: (i32, i32), facing: Cardinal) -> String {
+        let mut res = String::new();
+
+        for y in 0..=self.height {
+            // walls above the row
+            let mut line = String::new();
+            for x in 0..self.width {
+                let (above, below) = ((x, y - 1), (x, y));
+                let corner_known =
+                    self.is_known(above, below) || self.is_known((x - 1, y - 1), (x - 1, y));
+                line.push(if corner_known { '+' } else { ' ' });
+
+                if !self.is_known(above, below) {
+                    line.push_str("   ");
+                } else if self.is_locked(above, Cardinal::South) {
+                    line.push_str(" # ");
+                } else if self.is_open(above, Cardinal::South) {
+                    line.push_str("   ");
+                } else {
+                    line.push_str("---");
+                }
+            }
+            let last_corner_known = self.is_known((self.width - 1, y - 1), (self.width - 1, y));
+            line.push(if last_corner_known { '+' } else { ' ' });
+            Maze::push_line(&mut res, &line);
+
+            if y == self.height {
+                break;
+            }
+
+            // rooms of the row with the walls between them
+            let mut line = String::new();
+            for x in 0..=self.width {
+                let (left, right) = ((x - 1, y), (x, y));
+                if !self.is_known(left, right) {
+                    line.push(' ');
+                } else if self.is_locked(left, Cardinal::East) {
+                    line.push('#');
+                } else if self.is_open(left, Cardinal::East) {
+                    line.push(' ');
+                } else {
+                    line.push('|');
+                }
+
+                if x < self.width {
+                    line.push_str(&self.display_room(right, player, facing));
+                }
+            }
+            Maze::push_line(&mut res, &line);
+        }
+
+        res
+    }
+}
@@ -1,15 +1,26 @@
 mod art;
+mod classes;
 mod commands;
+mod companions;
 mod data;
+mod dialogues;
+mod effects;
 mod enemies;
+mod floors;
 mod game;
 mod items;
+mod journal;
+mod maze;
+mod puzzles;
+mod quests;
+mod skills;
+mod summary;
 mod utils;
 
 use bevy::{ecs::schedule::ShouldRun, prelude::*};
 use ron::de::from_bytes;
 
-use crate::{games::GameList, states::GameState};
+use crate::{console::event::PrintConsoleEvent, games::GameList, states::GameState};
 
 use super::ConsoleGamesData;
 
@@ -19,15 +30,28 @@ impl Plugin for LabyrinthGamePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(data::LabyrinthData::default());
         app.insert_resource(data::PlayerStats::default());
-        app.insert_resource(
-            from_bytes::<data::LabyrinthResourceFile>(include_bytes!(
-                "../../../data/labyrinth_data.ron"
-            ))
-            .unwrap(),
-        );
+        app.insert_resource(data::LabyrinthRng::default());
+        app.insert_resource(summary::LabyrinthHistory::default());
+
+        let mut laby_res = from_bytes::<data::LabyrinthResourceFile>(include_bytes!(
+            "../../../data/labyrinth_data.ron"
+        ))
+        .unwrap();
+        if let Err(msg) = laby_res.rules.validate() {
+            panic!("Invalid rules in labyrinth_data.ron: {}", msg);
+        }
+        laby_res.load_art();
+        app.insert_resource(laby_res);
+
         app.add_system_set(
             SystemSet::on_update(GameState::ConsoleOpenedState)
                 .with_run_criteria(should_run)
+                .with_system(game::clock_system)
+                .with_system(
+                    game::walking_system
+                        .label("laby_walking")
+                        .before("laby_game_loop"),
+                )
                 .with_system(
                     game::game_loop
                         .label("laby_game_loop")
@@ -42,7 +66,18 @@ impl Plugin for LabyrinthGamePlugin {
     }
 }
 
-pub fn start_game(cg_data: &mut ResMut<ConsoleGamesData>) {
+pub fn start_game(
+    cg_data: &mut ResMut<ConsoleGamesData>,
+    console_writer: &mut EventWriter<PrintConsoleEvent>,
+) {
+    if let Err(msg) = data::RunOptions::from_args(&cg_data.launch_args) {
+        console_writer.send(PrintConsoleEvent(msg));
+        console_writer.send(PrintConsoleEvent(
+            "Usage: play labyrinth [--seed N] [--endless | --speedrun]".to_string(),
+        ));
+        return;
+    }
+
     cg_data.loaded_game = GameList::Labyrinth;
     #[cfg(debug_assertions)]
     info!("Starting labyrinth game");
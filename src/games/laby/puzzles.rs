@@ -0,0 +1,108 @@
+use serde::Deserialize;
+
+use super::{data::PlayerStats, effects::StatusEffect, items::Item};
+
+/// What happens once a puzzle room is solved or failed
+#[derive(Debug, Deserialize, Clone)]
+pub struct RoomOutcome {
+    pub message: String,
+    #[serde(default)]
+    pub damages: f32,
+    #[serde(default)]
+    pub heal: f32,
+    #[serde(default)]
+    pub exp: usize,
+    #[serde(default)]
+    pub gold: usize,
+    // name of an item of the items list
+    #[serde(default)]
+    pub item: Option<String>,
+    #[serde(default)]
+    pub effect: Option<StatusEffect>,
+}
+
+/// A door that only opens for the right answer
+#[derive(Debug, Deserialize, Clone)]
+pub struct Riddle {
+    pub question: String,
+    // any of them is accepted, in lowercase
+    pub answers: Vec<String>,
+    pub success: RoomOutcome,
+    // a wrong answer, or walking past the door
+    pub failure: RoomOutcome,
+}
+
+/// A pressure plate hidden in the floor
+#[derive(Debug, Deserialize, Clone)]
+pub struct Trap {
+    pub description: String,
+    // what 'inspect' reveals
+    pub hint: String,
+    pub disarm_chance: f64,
+    // the chance once the trap was inspected
+    pub inspected_chance: f64,
+    pub success: RoomOutcome,
+    // a failed disarm, or walking on the plate
+    pub failure: RoomOutcome,
+}
+
+/// A gate blocking the way, opened with a key
+#[derive(Debug, Deserialize, Clone)]
+pub struct Gate {
+    pub description: String,
+    // chance to open it without a key
+    pub force_chance: f64,
+    pub success: RoomOutcome,
+    // a failed attempt to force it
+    pub failure: RoomOutcome,
+}
+
+impl RoomOutcome {
+    /// Returns what happened, one line each
+    pub fn apply(&self, player: &mut PlayerStats, items: &[Item]) -> Vec<String> {
+        let mut res = vec![self.message.clone()];
+
+        if self.damages > 0.0 {
+            player.health = (player.health - self.damages).max(0.0);
+            res.push(format!("You lose {} HP", self.damages));
+        }
+        if self.heal > 0.0 {
+            player.health = (player.health + self.heal).min(player.max_health);
+            res.push(format!("You heal {} HP", self.heal));
+        }
+        if self.exp > 0 || self.gold > 0 {
+            player.exp += self.exp;
+            player.gold += self.gold;
+            res.push(format!(
+                "You gained {} Exp and {} gold",
+                self.exp, self.gold
+            ));
+        }
+        if let Some(item) = self
+            .item
+            .as_ref()
+            .and_then(|name| Item::find_by_name(items, name))
+        {
+            match player.add_item(item.clone()) {
+                Ok(()) => res.push(format!("You found a {}", item.name)),
+                Err(msg) => res.push(msg),
+            }
+        }
+        if let Some(effect) = self.effect {
+            player.effects.apply(effect);
+            res.push(format!(
+                "You are {}!",
+                effect.kind.to_display_str().to_lowercase()
+            ));
+        }
+
+        res
+    }
+}
+
+impl Riddle {
+    pub fn is_answer(&self, answer: &str) -> bool {
+        let answer = answer.trim().to_lowercase();
+        self.answers.contains(&answer)
+    }
+}
@@ -0,0 +1,175 @@
+use rand::{prelude::SliceRandom, Rng};
+use serde::Deserialize;
+
+use super::{enemies::EnemyType, items::ItemType, maze::Maze};
+
+/// A request an NPC can make, defined in the labyrinth data file
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuestTemplate {
+    // what the NPC asks, "{name}" is replaced by their username
+    pub request: String,
+    pub goal: QuestGoalTemplate,
+    #[serde(default)]
+    pub reward: QuestReward,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum QuestGoalTemplate {
+    Kill { enemy: EnemyType, count: usize },
+    // the item has to be brought back to the room of the NPC
+    Bring(ItemType),
+    // a room at least this many rooms deeper than the NPC
+    Reach(usize),
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct QuestReward {
+    #[serde(default)]
+    pub exp: usize,
+    #[serde(default)]
+    pub gold: usize,
+    // name of an item of the items list
+    #[serde(default)]
+    pub item: Option<String>,
+    // added to the relationship with the NPC
+    #[serde(default)]
+    pub friendship: i32,
+}
+
+#[derive(Debug, Clone)]
+pub enum QuestGoal {
+    Kill { enemy: EnemyType, count: usize },
+    Bring { item: ItemType, room: (i32, i32) },
+    Reach { room: (i32, i32) },
+}
+
+/// A quest the player accepted
+#[derive(Debug, Clone)]
+pub struct Quest {
+    pub giver: String,
+    pub request: String,
+    pub goal: QuestGoal,
+    pub reward: QuestReward,
+    pub progress: usize,
+    pub is_done: bool,
+}
+
+impl Quest {
+    /// Picks a request of the NPC standing in this room, if they have one
+    /// that makes sense in this maze
+    pub fn new_random(
+        templates: &[QuestTemplate],
+        giver: &str,
+        maze: &Maze,
+        position: (i32, i32),
+        rng: &mut impl Rng,
+    ) -> Option<Quest> {
+        let template = templates.choose(rng)?;
+
+        let goal = match &template.goal {
+            QuestGoalTemplate::Kill { enemy, count } => QuestGoal::Kill {
+                enemy: enemy.clone(),
+                count: *count,
+            },
+            QuestGoalTemplate::Bring(item) => QuestGoal::Bring {
+                item: *item,
+                room: position,
+            },
+            QuestGoalTemplate::Reach(distance) => {
+                let depth = maze.get_depth(position);
+                let rooms: Vec<(i32, i32)> = (0..maze.height)
+                    .flat_map(|y| (0..maze.width).map(move |x| (x, y)))
+                    .filter(|room| {
+                        !maze.is_visited(*room)
+                            && *room != maze.exit
+                            && maze.get_depth(*room) >= depth + distance
+                    })
+                    .collect();
+                QuestGoal::Reach {
+                    room: *rooms.choose(rng)?,
+                }
+            }
+        };
+
+        Some(Quest {
+            giver: giver.to_string(),
+            request: template.request.replace("{name}", giver),
+            goal,
+            reward: template.reward.clone(),
+            progress: 0,
+            is_done: false,
+        })
+    }
+
+    /// Returns true when this kill completes the quest
+    pub fn on_kill(&mut self, kind: &EnemyType) -> bool {
+        if let QuestGoal::Kill { enemy, count } = &self.goal {
+            if !self.is_done && enemy == kind {
+                self.progress += 1;
+                self.is_done = self.progress >= *count;
+                return self.is_done;
+            }
+        }
+        false
+    }
+
+    /// Returns true when entering this room completes the quest,
+    /// `has_item` tells if the player carries the item to bring
+    pub fn on_enter_room(&mut self, position: (i32, i32), has_item: bool) -> bool {
+        if self.is_done {
+            return false;
+        }
+
+        self.is_done = match &self.goal {
+            QuestGoal::Bring { room, .. } => *room == position && has_item,
+            QuestGoal::Reach { room } => *room == position,
+            QuestGoal::Kill { .. } => false,
+        };
+        self.is_done
+    }
+
+    pub fn to_display(&self, position: (i32, i32)) -> String {
+        let goal = match &self.goal {
+            QuestGoal::Kill { enemy, count } => {
+                format!("Hunt the {}s ({}/{})", enemy.0, self.progress, count)
+            }
+            QuestGoal::Bring { item, room } => format!(
+                "Bring a {} back to {} ({})",
+                item.to_display_str(),
+                self.giver,
+                display_offset(position, *room)
+            ),
+            QuestGoal::Reach { room } => {
+                format!("Reach the room {} alive", display_offset(position, *room))
+            }
+        };
+
+        format!("{} - asked by {}", goal, self.giver)
+    }
+}
+
+// where a room is from the player, like "2 north, 1 east"
+fn display_offset(from: (i32, i32), to: (i32, i32)) -> String {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let mut parts = Vec::new();
+    if dy != 0 {
+        parts.push(format!(
+            "{} {}",
+            dy.abs(),
+            if dy < 0 { "north" } else { "south" }
+        ));
+    }
+    if dx != 0 {
+        parts.push(format!(
+            "{} {}",
+            dx.abs(),
+            if dx < 0 { "west" } else { "east" }
+        ));
+    }
+
+    if parts.is_empty() {
+        "right here".to_string()
+    } else {
+        format!("{} of you", parts.join(", "))
+    }
+}
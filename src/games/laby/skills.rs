@@ -0,0 +1,79 @@
+use serde::Deserialize;
+
+use super::data::LevelBonus;
+
+/// A stat the player can raise with a skill point
+#[derive(Debug, Deserialize, Clone)]
+pub struct StatUpgrade {
+    // typed after 'spend'
+    pub name: String,
+    pub description: String,
+    pub bonus: LevelBonus,
+}
+
+/// A perk bought with skill points, it lasts for the whole run
+#[derive(Debug, Deserialize, Clone)]
+pub struct Skill {
+    // typed after 'learn'
+    pub name: String,
+    pub description: String,
+    pub cost: usize,
+    #[serde(default = "default_min_level")]
+    pub min_level: usize,
+    pub kind: SkillKind,
+    pub power: f32,
+}
+
+fn default_min_level() -> usize {
+    1
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum SkillKind {
+    // removes `power` damages of each hit taken
+    Toughness,
+    // heals `power` HP after each kill
+    Vampirism,
+    // heals `power` more HP in each new room
+    Recovery,
+    // the ability of the class is ready `power` turns sooner
+    Focus,
+}
+
+/// How much exp each level needs, it grows with the level
+#[derive(Debug, Deserialize)]
+pub struct ExpCurve {
+    // exp needed to reach level 2
+    pub base: usize,
+    // the exp needed is multiplied by it on each level
+    pub growth: f32,
+}
+
+impl ExpCurve {
+    pub fn get_exp_to_level_up(&self, level: usize) -> usize {
+        let exp = self.base as f32 * self.growth.powi(level as i32 - 1);
+        (exp.round() as usize).max(1)
+    }
+}
+
+impl StatUpgrade {
+    pub fn to_display(&self) -> String {
+        format!("{}: {}", self.name, self.description)
+    }
+}
+
+impl Skill {
+    pub fn to_display(&self) -> String {
+        let mut res = format!(
+            "{} ({} pt{}): {}",
+            self.name,
+            self.cost,
+            if self.cost > 1 { "s" } else { "" },
+            self.description
+        );
+        if self.min_level > 1 {
+            res.push_str(&format!(" - needs level {}", self.min_level));
+        }
+        res
+    }
+}
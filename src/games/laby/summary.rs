@@ -0,0 +1,274 @@
+use std::{cmp::Reverse, time::Duration};
+
+use super::data::{LabyrinthData, PlayerStats, RunMode};
+
+// how many runs each leaderboard shows
+const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunEnding {
+    Victory,
+    Death,
+    Ragequit,
+}
+
+impl RunEnding {
+    pub fn to_display_str(self) -> &'static str {
+        match self {
+            RunEnding::Victory => "Victory",
+            RunEnding::Death => "Death",
+            RunEnding::Ragequit => "Ragequit",
+        }
+    }
+}
+
+/// The time at which a floor was left
+#[derive(Debug, Clone)]
+pub struct Split {
+    pub floor: String,
+    pub time: Duration,
+    // minutes on the clock of the apartment
+    pub game_time: f32,
+}
+
+/// The report of a finished run
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    pub ending: RunEnding,
+    pub mode: RunMode,
+    pub class: Option<String>,
+    pub floor: usize,
+    pub level: usize,
+    pub seed: u64,
+    pub duration: Duration,
+    pub game_time: f32,
+    pub splits: Vec<Split>,
+    pub depth_score: usize,
+    pub rooms_cleared: usize,
+    // name of the enemy and how many were killed
+    pub kills: Vec<(String, usize)>,
+    pub damages_dealt: f32,
+    pub damages_taken: f32,
+    pub items_found: Vec<String>,
+    pub befriended: Vec<String>,
+    pub angered: Vec<String>,
+}
+
+/// Every run played since the game was launched
+#[derive(Debug, Default)]
+pub struct LabyrinthHistory {
+    pub runs: Vec<RunSummary>,
+}
+
+impl RunSummary {
+    pub fn new(
+        laby_data: &LabyrinthData,
+        player: &PlayerStats,
+        seed: u64,
+        ending: RunEnding,
+    ) -> Self {
+        let stats = &laby_data.run_stats;
+
+        RunSummary {
+            ending,
+            mode: laby_data.mode,
+            class: stats.class.clone(),
+            floor: laby_data.floor + 1,
+            level: player.level,
+            seed,
+            duration: stats.get_duration(),
+            game_time: stats.game_time,
+            splits: stats.splits.clone(),
+            depth_score: stats.depth_score,
+            rooms_cleared: stats.rooms_cleared,
+            kills: laby_data
+                .journal
+                .bestiary
+                .iter()
+                .filter(|entry| entry.kills > 0)
+                .map(|entry| (entry.name.clone(), entry.kills))
+                .collect(),
+            damages_dealt: stats.damages_dealt,
+            damages_taken: stats.damages_taken,
+            items_found: stats.items_found.clone(),
+            befriended: stats.befriended.clone(),
+            angered: stats.angered.clone(),
+        }
+    }
+
+    pub fn get_total_kills(&self) -> usize {
+        self.kills.iter().map(|(_, kills)| kills).sum()
+    }
+
+    pub fn to_display(&self) -> String {
+        let mut res = String::from("------------------=[Run summary]=-----------------\n\n");
+
+        res.push_str(&format!(
+            "{} ({}) - {} level {}, floor {}\n",
+            self.ending.to_display_str(),
+            self.mode.to_display_str(),
+            self.class.as_deref().unwrap_or("No class"),
+            self.level,
+            self.floor
+        ));
+        res.push_str(&format!(
+            "Time: {} (in-game {}) | Rooms cleared: {}\n",
+            display_duration(self.duration),
+            display_game_time(self.game_time),
+            self.rooms_cleared
+        ));
+        if self.mode == RunMode::Endless {
+            res.push_str(&format!("Depth score: {}\n", self.depth_score));
+        }
+        res.push_str(&format!(
+            "Damage dealt: {} | Damage taken: {}\n",
+            self.damages_dealt.round(),
+            self.damages_taken.round()
+        ));
+
+        res.push_str(&format!("\nKills ({}):\n", self.get_total_kills()));
+        for (name, kills) in self.kills.iter() {
+            res.push_str(&format!("- {} x{}\n", name, kills));
+        }
+        res.push_str(&format!(
+            "\nItems found: {}\n",
+            display_list(&self.items_found)
+        ));
+        res.push_str(&format!("Befriended: {}\n", display_list(&self.befriended)));
+        res.push_str(&format!("Angered: {}\n", display_list(&self.angered)));
+
+        if self.mode == RunMode::Speedrun {
+            res.push_str("\nSplits:\n");
+            res.push_str(&display_splits(&self.splits));
+        }
+
+        res.push_str(&format!(
+            "\nSeed of this run: {} (replay it with 'play labyrinth --seed {}')\n",
+            self.seed, self.seed
+        ));
+
+        res
+    }
+
+    /// One line for the history
+    pub fn to_short_display(&self) -> String {
+        let score = match self.mode {
+            RunMode::Endless => format!(", depth {}", self.depth_score),
+            _ => String::new(),
+        };
+        format!(
+            "{} ({}) - {} lvl {}, floor {}{}, {} kills in {} (seed {})",
+            self.ending.to_display_str(),
+            self.mode.to_display_str(),
+            self.class.as_deref().unwrap_or("No class"),
+            self.level,
+            self.floor,
+            score,
+            self.get_total_kills(),
+            display_duration(self.duration),
+            self.seed
+        )
+    }
+}
+
+impl LabyrinthHistory {
+    pub fn to_display(&self) -> String {
+        let mut res = String::from("--------------------[History]---------------------\n\n");
+
+        if self.runs.is_empty() {
+            res.push_str("No run finished yet...\n");
+        }
+        for (i, run) in self.runs.iter().enumerate() {
+            res.push_str(&format!("{}. {}\n", i + 1, run.to_short_display()));
+        }
+        res.push_str("\nType 'history endless' or 'history speedrun' for the leaderboards\n");
+
+        res
+    }
+
+    /// The deepest endless runs, or the fastest won speedruns
+    pub fn get_leaderboard(&self, mode: RunMode) -> Vec<&RunSummary> {
+        let mut runs: Vec<&RunSummary> = self.runs.iter().filter(|run| run.mode == mode).collect();
+
+        match mode {
+            RunMode::Endless => runs.sort_by_key(|run| Reverse(run.depth_score)),
+            _ => {
+                runs.retain(|run| run.ending == RunEnding::Victory);
+                runs.sort_by_key(|run| run.duration);
+            }
+        }
+        runs.truncate(LEADERBOARD_SIZE);
+
+        runs
+    }
+
+    pub fn display_leaderboard(&self, mode: RunMode) -> String {
+        let mut res = format!(
+            "----------------[{} leaderboard]-----------------\n\n",
+            mode.to_display_str()
+        );
+
+        let runs = self.get_leaderboard(mode);
+        if runs.is_empty() {
+            res.push_str("No run to show yet...\n");
+        }
+        for (i, run) in runs.iter().enumerate() {
+            let score = match mode {
+                RunMode::Endless => format!("depth {}", run.depth_score),
+                _ => format!(
+                    "{} (in-game {})",
+                    display_duration(run.duration),
+                    display_game_time(run.game_time)
+                ),
+            };
+            res.push_str(&format!(
+                "{}. {} - {} lvl {} (seed {})\n",
+                i + 1,
+                score,
+                run.class.as_deref().unwrap_or("No class"),
+                run.level,
+                run.seed
+            ));
+        }
+
+        res
+    }
+}
+
+/// Each floor with the time it was left at and how long it took
+pub fn display_splits(splits: &[Split]) -> String {
+    let mut res = String::new();
+    let mut last = Duration::default();
+
+    for split in splits.iter() {
+        res.push_str(&format!(
+            "- {}: {} (+{}, in-game {})\n",
+            split.floor,
+            display_duration(split.time),
+            display_duration(split.time.saturating_sub(last)),
+            display_game_time(split.game_time)
+        ));
+        last = split.time;
+    }
+
+    res
+}
+
+pub fn display_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}m{:02}s", seconds / 60, seconds % 60)
+}
+
+// the clock of the apartment, in hours and minutes
+pub fn display_game_time(minutes: f32) -> String {
+    let minutes = minutes as usize;
+    format!("{}h{:02}", minutes / 60, minutes % 60)
+}
+
+fn display_list(names: &[String]) -> String {
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
@@ -23,6 +23,7 @@ impl Plugin for ConsoleGamesPlugin {
             loaded_game: GameList::None,
             ragequit_count: 0,
             has_won_laby: false,
+            launch_args: Vec::new(),
         });
         app.add_startup_system(setup);
         app.add_plugin(laby::LabyrinthGamePlugin);
@@ -34,6 +35,8 @@ pub struct ConsoleGamesData {
     pub loaded_game: GameList,
     pub ragequit_count: usize,
     pub has_won_laby: bool,
+    // options given after the game name in the 'play' command
+    pub launch_args: Vec<String>,
 }
 
 impl ConsoleGamesData {
@@ -67,9 +70,11 @@ pub fn handle_play_command(
         return;
     }
 
+    cg_data.launch_args = args[2..].iter().map(|arg| arg.to_lowercase()).collect();
+
     match args[1].to_lowercase().as_str() {
-        "labyrinth" => laby::start_game(cg_data),
-        "tictactoe" => tictactoe::start_game(cg_data),
+        "labyrinth" => laby::start_game(cg_data, console_writer),
+        "tictactoe" => tictactoe::start_game(cg_data, console_writer),
 
         _ => {
             console_writer.send(PrintConsoleEvent(format!(
@@ -86,7 +91,12 @@ fn print_games_list(console_writer: &mut EventWriter<PrintConsoleEvent>) {
     res.push_str("CONSOLE GAMES INSTALLED\n");
     res.push_str("=======================\n");
     res.push_str("- Labyrinth: a labyrinth game\n");
-    res.push_str("- TicTacToe: you noe it\n\n");
+    res.push_str("    options: --seed N (replay a run),\n");
+    res.push_str("             --endless | --speedrun\n");
+    res.push_str("- TicTacToe: you noe it\n");
+    res.push_str("    options: --size N (3-9), --align K (3-N),\n");
+    res.push_str("             --rounds N (best of N), --vs <username>,\n");
+    res.push_str("             --variant classic|ultimate\n\n");
 
     console_writer.send(PrintConsoleEvent(res));
 }
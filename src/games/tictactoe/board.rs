@@ -0,0 +1,288 @@
+use rand::{prelude::SliceRandom, Rng};
+
+// cell values
+pub const EMPTY: usize = 0;
+pub const PLAYER: usize = 1;
+pub const AI: usize = 2;
+// a drawn board inside the ultimate grid, nobody can use it
+pub const DRAW: usize = 3;
+
+// columns go from 'A' to 'I' and rows from 1 to 9
+pub const MIN_SIZE: usize = 3;
+pub const MAX_SIZE: usize = 9;
+pub const DEFAULT_SIZE: usize = 3;
+pub const DEFAULT_WIN_LENGTH: usize = 3;
+
+// horizontal, vertical and both diagonals
+const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+/// How the AI weighs its options when picking a cell
+#[derive(Debug, Clone, Copy)]
+pub struct AIProfile {
+    // 1 chance out of mistake_ratio that the AI plays a random cell
+    pub mistake_ratio: u32,
+    // how much the AI cares about its own lines
+    pub attack_weight: usize,
+    // how much the AI cares about the player's lines
+    pub defense_weight: usize,
+}
+
+impl Default for AIProfile {
+    fn default() -> Self {
+        AIProfile {
+            mistake_ratio: 4,
+            attack_weight: 1,
+            defense_weight: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Board {
+    pub size: usize,
+    pub win_length: usize,
+    // 0 = empty, 1 = player, 2 = ai, 3 = draw
+    pub grid: Vec<Vec<usize>>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new(DEFAULT_SIZE, DEFAULT_WIN_LENGTH)
+    }
+}
+
+impl Board {
+    pub fn new(size: usize, win_length: usize) -> Self {
+        Board {
+            size,
+            win_length,
+            grid: vec![vec![EMPTY; size]; size],
+        }
+    }
+
+    /// Parses a coordinate like "b3" (column letter then row number)
+    /// into a (row, column) position
+    pub fn get_position(&self, coords: &str) -> Option<(usize, usize)> {
+        let coords = coords.to_lowercase();
+        let mut chars = coords.chars();
+
+        let column = chars.next()?;
+        if !column.is_ascii_lowercase() {
+            return None;
+        }
+        let j = (column as u8 - b'a') as usize;
+        let i = chars.as_str().parse::<usize>().ok()?.checked_sub(1)?;
+
+        if i < self.size && j < self.size {
+            Some((i, j))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_position_name(i: usize, j: usize) -> String {
+        format!("{}{}", (b'A' + j as u8) as char, i + 1)
+    }
+
+    pub fn last_position_name(&self) -> String {
+        Board::get_position_name(self.size - 1, self.size - 1)
+    }
+
+    pub fn get_pawn(&self, i: usize, j: usize) -> &str {
+        match self.grid[i][j] {
+            EMPTY => " ",
+            PLAYER => "X",
+            AI => "O",
+            _ => "#",
+        }
+    }
+
+    pub fn count_pawns(&self, pawn: usize) -> usize {
+        self.grid
+            .iter()
+            .flatten()
+            .filter(|cell| **cell == pawn)
+            .count()
+    }
+
+    pub fn has_space_available(&self) -> bool {
+        self.grid.iter().flatten().any(|cell| *cell == EMPTY)
+    }
+
+    pub fn free_cells(&self) -> Vec<(usize, usize)> {
+        let mut res = Vec::new();
+        for i in 0..self.size {
+            for j in 0..self.size {
+                if self.grid[i][j] == EMPTY {
+                    res.push((i, j));
+                }
+            }
+        }
+        res
+    }
+
+    fn get_cell(&self, i: isize, j: isize) -> Option<usize> {
+        if i < 0 || j < 0 || i >= self.size as isize || j >= self.size as isize {
+            return None;
+        }
+        Some(self.grid[i as usize][j as usize])
+    }
+
+    // number of identical pawns in a row starting at (i, j) in a direction
+    fn count_in_direction(&self, i: usize, j: usize, (di, dj): (isize, isize)) -> usize {
+        let pawn = self.grid[i][j];
+        let mut count = 0;
+        let (mut x, mut y) = (i as isize, j as isize);
+
+        while self.get_cell(x, y) == Some(pawn) {
+            count += 1;
+            x += di;
+            y += dj;
+        }
+        count
+    }
+
+    pub fn has_winner(&self) -> usize {
+        for i in 0..self.size {
+            for j in 0..self.size {
+                if self.grid[i][j] != PLAYER && self.grid[i][j] != AI {
+                    continue;
+                }
+
+                if DIRECTIONS
+                    .iter()
+                    .any(|dir| self.count_in_direction(i, j, *dir) >= self.win_length)
+                {
+                    return self.grid[i][j];
+                }
+            }
+        }
+
+        0
+    }
+
+    // number of `pawn` in a row next to (i, j) in a direction, (i, j) excluded
+    fn count_next_to(&self, i: usize, j: usize, (di, dj): (isize, isize), pawn: usize) -> usize {
+        let mut count = 0;
+        let (mut x, mut y) = (i as isize + di, j as isize + dj);
+
+        while self.get_cell(x, y) == Some(pawn) {
+            count += 1;
+            x += di;
+            y += dj;
+        }
+        count
+    }
+
+    // only the lines going through (i, j) are checked, as if the pawn was there
+    pub fn is_winning_move(&self, i: usize, j: usize, pawn: usize) -> bool {
+        DIRECTIONS.iter().any(|&(di, dj)| {
+            let forward = self.count_next_to(i, j, (di, dj), pawn);
+            let backward = self.count_next_to(i, j, (-di, -dj), pawn);
+            forward + backward + 1 >= self.win_length
+        })
+    }
+
+    pub fn find_winning_move(&self, pawn: usize) -> Option<(usize, usize)> {
+        self.free_cells()
+            .into_iter()
+            .find(|(i, j)| self.is_winning_move(*i, *j, pawn))
+    }
+
+    // Scores a cell by looking at every line of `win_length` cells going
+    // through it: lines that can still be completed by one side are worth
+    // more the more pawns of that side they already hold.
+    pub fn cell_score(&self, i: usize, j: usize, profile: &AIProfile) -> usize {
+        let mut score = 0;
+
+        for &(di, dj) in DIRECTIONS.iter() {
+            for offset in 0..self.win_length as isize {
+                let start = (i as isize - di * offset, j as isize - dj * offset);
+
+                let mut counts = [0; 4];
+                let mut is_inside = true;
+                for step in 0..self.win_length as isize {
+                    match self.get_cell(start.0 + di * step, start.1 + dj * step) {
+                        Some(cell) => counts[cell] += 1,
+                        None => {
+                            is_inside = false;
+                            break;
+                        }
+                    }
+                }
+
+                if !is_inside || counts[DRAW] > 0 {
+                    continue;
+                }
+                if counts[PLAYER] == 0 {
+                    score += profile.attack_weight * 4usize.pow(counts[AI] as u32);
+                }
+                if counts[AI] == 0 {
+                    score += profile.defense_weight * 4usize.pow(counts[PLAYER] as u32);
+                }
+            }
+        }
+
+        score
+    }
+
+    /// Picks the AI move: win if possible, block the player if needed,
+    /// otherwise take the most promising cell. Each cell only looks at the
+    /// lines going through it, so the work is linear in the number of cells
+    /// (times the win length) and stays cheap even on a 9x9 board.
+    pub fn pick_ai_move(&self, profile: &AIProfile) -> Option<(usize, usize)> {
+        let free_cells = self.free_cells();
+        if free_cells.is_empty() {
+            return None;
+        }
+
+        if let Some(position) = self.find_winning_move(AI) {
+            return Some(position);
+        }
+        if let Some(position) = self.find_winning_move(PLAYER) {
+            return Some(position);
+        }
+
+        // nobody's perfect
+        if rand::thread_rng().gen_ratio(1, profile.mistake_ratio) {
+            return free_cells.choose(&mut rand::thread_rng()).copied();
+        }
+
+        let scores: Vec<usize> = free_cells
+            .iter()
+            .map(|(i, j)| self.cell_score(*i, *j, profile))
+            .collect();
+        let best_score = *scores.iter().max()?;
+
+        let best_cells: Vec<(usize, usize)> = free_cells
+            .into_iter()
+            .zip(scores)
+            .filter(|(_, score)| *score == best_score)
+            .map(|(position, _)| position)
+            .collect();
+
+        best_cells.choose(&mut rand::thread_rng()).copied()
+    }
+
+    pub fn display(&self) -> String {
+        let columns: Vec<String> = (0..self.size)
+            .map(|j| format!(" {} ", (b'A' + j as u8) as char))
+            .collect();
+        let separator = vec!["---"; self.size].join("+");
+
+        let mut res = format!("   {}\n", columns.join(" "));
+        for i in 0..self.size {
+            let cells: Vec<String> = (0..self.size)
+                .map(|j| format!(" {} ", self.get_pawn(i, j)))
+                .collect();
+            res.push_str(&format!("{:<3}{}\n", i + 1, cells.join("|")));
+
+            if i + 1 < self.size {
+                res.push_str(&format!("   {}\n", separator));
+            }
+        }
+        res.push('\n');
+
+        res
+    }
+}
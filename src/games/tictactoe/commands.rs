@@ -6,9 +6,11 @@ use crate::{
         ConsoleData,
     },
     games::{ConsoleGamesData, GameList},
+    npcs::Interaction,
+    vulnerability::VulnerabilityResource,
 };
 
-use super::game::{self, TicTacToeData};
+use super::game::{self, TicTacToeData, TurnType};
 
 pub fn commands_handler(
     mut cmd_reader: EventReader<EnteredConsoleCommandEvent>,
@@ -16,6 +18,7 @@ pub fn commands_handler(
     mut cg_data: ResMut<ConsoleGamesData>,
     mut data: ResMut<ConsoleData>,
     mut ttt_data: ResMut<TicTacToeData>,
+    mut vuln_res: ResMut<VulnerabilityResource>,
 ) {
     for EnteredConsoleCommandEvent(cmd) in cmd_reader.iter() {
         // Don't do anything if the string is empty
@@ -36,23 +39,89 @@ pub fn commands_handler(
             "clear" => data.messages.clear(),
             "help" => console_writer.send(PrintConsoleEvent(display_help())),
             "ragequit" => {
+                // leaving once the match is over isn't rage
+                if ttt_data.current_turn != TurnType::Finished {
+                    cg_data.ragequit_count += 1;
+                }
                 ttt_data.reset();
                 console_writer.send(PrintConsoleEvent("Quitting TicTacToe...".to_string()));
                 cg_data.loaded_game = GameList::None;
-                cg_data.ragequit_count += 1;
             }
-            "tutorial" => console_writer.send(PrintConsoleEvent(game::display_tutorial())),
+            "tutorial" => console_writer.send(PrintConsoleEvent(match &ttt_data.ultimate {
+                Some(ultimate) => game::display_ultimate_tutorial(ultimate),
+                None => game::display_tutorial(&ttt_data.board),
+            })),
 
             "place" => {
                 if args.len() == 1 {
                     console_writer.send(PrintConsoleEvent(
-                        "No position provided\nUsage: place <pos>".to_string(),
+                        "No position provided\nUsage: place <pos> (ex: place B2, or B2A1 in ultimate)".to_string(),
                     ));
                     return;
                 }
 
                 game::play_position(args[1], &mut ttt_data, &mut console_writer);
             }
+            "undo" => {
+                if ttt_data.current_turn != TurnType::PlayerTurn || !ttt_data.waiting_for_input {
+                    console_writer.send(PrintConsoleEvent(
+                        "You can only undo during your turn".to_string(),
+                    ));
+                } else if ttt_data.undos_left == 0 {
+                    console_writer.send(PrintConsoleEvent(
+                        "You have no undo left for this match".to_string(),
+                    ));
+                } else if ttt_data.undo() {
+                    console_writer.send(PrintConsoleEvent(format!(
+                        "You take back your last move... ({} undo left)",
+                        ttt_data.undos_left
+                    )));
+                } else {
+                    console_writer.send(PrintConsoleEvent(
+                        "There is nothing to undo in this round".to_string(),
+                    ));
+                }
+            }
+            "replay" => {
+                if ttt_data.current_turn == TurnType::Finished {
+                    console_writer.send(PrintConsoleEvent(game::display_replay(&ttt_data)));
+                } else {
+                    console_writer.send(PrintConsoleEvent(
+                        "The replay will be available at the end of the match".to_string(),
+                    ));
+                }
+            }
+            "gloat" => {
+                if ttt_data.current_turn == TurnType::Finished
+                    && ttt_data.player_score > ttt_data.opponent_score
+                {
+                    console_writer.send(PrintConsoleEvent(format!(
+                        "You laugh at {} in the chat... They won't forget it.",
+                        ttt_data.get_opponent_name()
+                    )));
+                    game::update_relationship(&ttt_data, &mut vuln_res, Interaction::Gloat);
+                    cg_data.loaded_game = GameList::None;
+                    ttt_data.reset();
+                } else {
+                    console_writer.send(PrintConsoleEvent(
+                        "There is nothing to gloat about... yet".to_string(),
+                    ));
+                }
+            }
+            "gg" => {
+                if ttt_data.current_turn == TurnType::Finished {
+                    console_writer.send(PrintConsoleEvent(format!(
+                        "{}: \"gg wp\"",
+                        ttt_data.get_opponent_name()
+                    )));
+                    game::update_relationship(&ttt_data, &mut vuln_res, Interaction::GoodGame);
+                    cg_data.loaded_game = GameList::None;
+                    ttt_data.reset();
+                } else {
+                    console_writer
+                        .send(PrintConsoleEvent("The game isn't over yet...".to_string()));
+                }
+            }
 
             _ => {
                 console_writer.send(PrintConsoleEvent(format!(
@@ -73,7 +142,15 @@ fn display_help() -> String {
     res.push_str("- help : Displays this message\n");
     res.push_str("- clear : Clears commands on the screen\n");
     res.push_str("- tutorial : Show the tutorial for this game\n");
-    res.push_str("- place <pos> : Place a pawn at the position <pos>\n");
+    res.push_str("- place <pos> : Place a pawn at the position <pos> (ex: B2)\n");
+    res.push_str("  in ultimate, <pos> is <board><cell> (ex: B2A1)\n");
+    res.push_str(&format!(
+        "- undo : Takes back your last move ({} per match)\n",
+        game::MAX_UNDOS
+    ));
+    res.push_str("- replay : Shows the moves of the match once it's over\n");
+    res.push_str("- gloat : Mocks your opponent after a win\n");
+    res.push_str("- gg : Leaves the finished match like a good sport\n");
     res.push_str("- ragequit : Leaves the game (you will lose your progress)\n");
 
     res
@@ -1,94 +1,226 @@
 use bevy::prelude::*;
-use rand::Rng;
+
+use rand::prelude::IteratorRandom;
 
 use crate::{
     console::event::PrintConsoleEvent,
     games::{ConsoleGamesData, GameList},
+    npcs::{Interaction, NPCsResource},
     vulnerability::{BoolVulnerabilityType, VulnerabilityResource},
 };
 
-#[derive(Default)]
+use super::{
+    board::{self, Board},
+    opponent::Opponent,
+    ultimate::UltimateBoard,
+};
+
+pub const MAX_ROUNDS: usize = 9;
+// how many moves the player can take back during a match
+pub const MAX_UNDOS: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Move {
+    pub pawn: usize,
+    pub position: (usize, usize),
+}
+
 pub struct TicTacToeData {
     pub has_seen_tutorial: bool,
-    // 0 = empty, 1 = player, 2 = ai
-    pub grid: [[usize; 3]; 3],
+    pub board: Board,
+    // only used by the ultimate variant
+    pub ultimate: Option<UltimateBoard>,
+    pub opponent: Option<Opponent>,
     pub current_turn: TurnType,
     pub waiting_for_input: bool,
-    pub turn_number: usize,
+    // best of `rounds`
+    pub rounds: usize,
+    pub round_number: usize,
+    pub player_score: usize,
+    pub opponent_score: usize,
+    // the moves of every round, the last one being the current round
+    pub history: Vec<Vec<Move>>,
+    pub undos_left: usize,
+}
+
+impl Default for TicTacToeData {
+    fn default() -> Self {
+        TicTacToeData {
+            has_seen_tutorial: false,
+            board: Board::default(),
+            ultimate: None,
+            opponent: None,
+            current_turn: TurnType::default(),
+            waiting_for_input: false,
+            rounds: 1,
+            round_number: 0,
+            player_score: 0,
+            opponent_score: 0,
+            history: Vec::new(),
+            undos_left: MAX_UNDOS,
+        }
+    }
 }
 
 impl TicTacToeData {
     pub fn reset(&mut self) {
         self.has_seen_tutorial = false;
-        self.grid = [[0; 3]; 3];
+        self.board = Board::default();
+        self.ultimate = None;
+        self.opponent = None;
         self.current_turn = TurnType::PlayerTurn;
         self.waiting_for_input = false;
-        self.turn_number = 0;
+        self.rounds = 1;
+        self.round_number = 0;
+        self.player_score = 0;
+        self.opponent_score = 0;
+        self.history.clear();
+        self.undos_left = MAX_UNDOS;
     }
 
-    fn get_position(letter: &str) -> Option<(usize, usize)> {
-        match letter.to_lowercase().as_str() {
-            "a" => Some((0, 0)),
-            "b" => Some((0, 1)),
-            "c" => Some((0, 2)),
-            "d" => Some((1, 0)),
-            "e" => Some((1, 1)),
-            "f" => Some((1, 2)),
-            "g" => Some((2, 0)),
-            "h" => Some((2, 1)),
-            "i" => Some((2, 2)),
-            _ => None,
+    pub fn get_opponent_name(&self) -> &str {
+        match &self.opponent {
+            Some(opponent) => &opponent.username,
+            None => "The computer",
         }
     }
 
-    fn get_pawn(&self, i: usize, j: usize) -> &str {
-        match self.grid[i][j] {
-            0 => " ",
-            1 => "X",
-            _ => "O",
+    pub fn turn_number(&self) -> usize {
+        self.history.last().map_or(0, |moves| moves.len()) / 2 + 1
+    }
+
+    // the player starts the odd rounds and the opponent the even ones
+    fn start_round(&mut self) {
+        self.round_number += 1;
+        self.board = Board::new(self.board.size, self.board.win_length);
+        if self.ultimate.is_some() {
+            self.ultimate = Some(UltimateBoard::default());
         }
+        self.history.push(Vec::new());
+        self.current_turn = if self.round_number % 2 == 1 {
+            TurnType::PlayerTurn
+        } else {
+            TurnType::AITurn
+        };
+        self.waiting_for_input = false;
     }
 
-    fn has_space_available(&self) -> bool {
-        for i in 0..3 {
-            for j in 0..3 {
-                if self.grid[i as usize][j as usize] == 0 {
-                    return true;
-                }
+    pub fn play_move(&mut self, pawn: usize, (i, j): (usize, usize)) {
+        match &mut self.ultimate {
+            Some(ultimate) => {
+                ultimate.set_cell((i, j), pawn);
+                ultimate.last_move = Some((i, j));
             }
+            None => self.board.grid[i][j] = pawn,
+        }
+        if let Some(moves) = self.history.last_mut() {
+            moves.push(Move {
+                pawn,
+                position: (i, j),
+            });
         }
-        false
     }
 
-    fn has_winner(&self) -> usize {
-        for k in 0..3 {
-            if self.grid[k][0] == self.grid[k][1]
-                && self.grid[k][1] == self.grid[k][2]
-                && self.grid[k][0] != 0
-            {
-                return self.grid[k][0];
+    /// Takes back the last player move of the round along with
+    /// the opponent's answer, returns false if there is nothing to undo
+    /// or if the player has no undo left for this match
+    pub fn undo(&mut self) -> bool {
+        if self.undos_left == 0 {
+            return false;
+        }
+
+        let moves = match self.history.last_mut() {
+            Some(moves) if moves.iter().any(|m| m.pawn == board::PLAYER) => moves,
+            _ => return false,
+        };
+
+        while let Some(last_move) = moves.pop() {
+            let (i, j) = last_move.position;
+            match &mut self.ultimate {
+                Some(ultimate) => ultimate.set_cell((i, j), board::EMPTY),
+                None => self.board.grid[i][j] = board::EMPTY,
             }
-            if self.grid[0][k] == self.grid[1][k]
-                && self.grid[1][k] == self.grid[2][k]
-                && self.grid[0][k] != 0
-            {
-                return self.grid[0][k];
+
+            if last_move.pawn == board::PLAYER {
+                break;
             }
         }
-        if ((self.grid[0][0] == self.grid[1][1] && self.grid[1][1] == self.grid[2][2])
-            || (self.grid[2][0] == self.grid[1][1] && self.grid[1][1] == self.grid[0][2]))
-            && self.grid[1][1] != 0
-        {
-            return self.grid[1][1];
+        if let Some(ultimate) = &mut self.ultimate {
+            ultimate.last_move = moves.last().map(|m| m.position);
+        }
+
+        self.undos_left -= 1;
+        self.current_turn = TurnType::PlayerTurn;
+        self.waiting_for_input = false;
+        true
+    }
+
+    fn rounds_to_win(&self) -> usize {
+        self.rounds / 2 + 1
+    }
+
+    pub fn has_winner(&self) -> usize {
+        match &self.ultimate {
+            Some(ultimate) => ultimate.has_winner(),
+            None => self.board.has_winner(),
+        }
+    }
+
+    pub fn has_space_available(&self) -> bool {
+        match &self.ultimate {
+            Some(ultimate) => ultimate.has_space_available(),
+            None => self.board.has_space_available(),
+        }
+    }
+
+    pub fn pick_ai_move(&self) -> Option<(usize, usize)> {
+        let profile = match &self.opponent {
+            Some(opponent) => opponent.style.get_profile(),
+            None => board::AIProfile::default(),
+        };
+
+        match &self.ultimate {
+            Some(ultimate) => ultimate.pick_ai_move(&profile),
+            None => self.board.pick_ai_move(&profile),
+        }
+    }
+
+    pub fn get_position(&self, place: &str) -> Option<(usize, usize)> {
+        match &self.ultimate {
+            Some(ultimate) => ultimate.get_position(place),
+            None => self.board.get_position(place),
+        }
+    }
+
+    pub fn get_position_name(&self, position: (usize, usize)) -> String {
+        match &self.ultimate {
+            Some(_) => UltimateBoard::get_position_name(position),
+            None => Board::get_position_name(position.0, position.1),
+        }
+    }
+
+    pub fn valid_positions(&self) -> String {
+        match &self.ultimate {
+            Some(_) => "<board><cell>, ex: B2A1".to_string(),
+            None => format!("A1-{}", self.board.last_position_name()),
         }
+    }
 
-        0
+    // winning without a single wasted pawn is humiliating
+    pub fn is_crushing_win(&self) -> bool {
+        match &self.ultimate {
+            Some(ultimate) => ultimate.boards_won(board::AI) == 0,
+            None => self.board.count_pawns(board::PLAYER) == self.board.win_length,
+        }
     }
 }
 
+#[derive(PartialEq)]
 pub enum TurnType {
     PlayerTurn,
     AITurn,
+    // the match is over and the player can still react before leaving
+    Finished,
 }
 impl Default for TurnType {
     fn default() -> Self {
@@ -96,106 +228,379 @@ impl Default for TurnType {
     }
 }
 
-pub fn display_tutorial() -> String {
+// Options given to 'play tictactoe'
+pub struct MatchOptions {
+    pub size: usize,
+    pub win_length: usize,
+    pub rounds: usize,
+    pub opponent: Option<String>,
+    pub ultimate: bool,
+}
+
+impl MatchOptions {
+    /// Reads the `--size N`, `--align K`, `--rounds N`, `--vs <username>`
+    /// and `--variant <classic|ultimate>` options
+    pub fn from_args(args: &[String]) -> Result<MatchOptions, String> {
+        let mut options = MatchOptions {
+            size: board::DEFAULT_SIZE,
+            win_length: board::DEFAULT_WIN_LENGTH,
+            rounds: 1,
+            opponent: None,
+            ultimate: false,
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let value = iter
+                .next()
+                .ok_or_else(|| format!("Option '{}' expects a value", arg))?;
+
+            match arg.as_str() {
+                "--size" | "--align" | "--rounds" => {
+                    let number = value
+                        .parse::<usize>()
+                        .map_err(|_| format!("Option '{}' expects a number", arg))?;
+
+                    match arg.as_str() {
+                        "--size" => options.size = number,
+                        "--align" => options.win_length = number,
+                        _ => options.rounds = number,
+                    }
+                }
+                "--vs" => options.opponent = Some(value.clone()),
+                "--variant" => match value.as_str() {
+                    "classic" => options.ultimate = false,
+                    "ultimate" => options.ultimate = true,
+                    _ => {
+                        return Err(format!(
+                            "Unknown variant '{}' (valid: classic, ultimate)",
+                            value
+                        ))
+                    }
+                },
+                _ => return Err(format!("Unknown option '{}'", arg)),
+            }
+        }
+
+        if !(board::MIN_SIZE..=board::MAX_SIZE).contains(&options.size) {
+            return Err(format!(
+                "Invalid board size {} (valid: {}-{})",
+                options.size,
+                board::MIN_SIZE,
+                board::MAX_SIZE
+            ));
+        }
+        if options.win_length < board::MIN_SIZE || options.win_length > options.size {
+            return Err(format!(
+                "Can't align {} pawns on a {}x{} board (valid: {}-{})",
+                options.win_length,
+                options.size,
+                options.size,
+                board::MIN_SIZE,
+                options.size
+            ));
+        }
+        if options.ultimate
+            && (options.size != board::DEFAULT_SIZE
+                || options.win_length != board::DEFAULT_WIN_LENGTH)
+        {
+            return Err("Ultimate TicTacToe is only played with 3x3 boards".to_string());
+        }
+        if options.rounds % 2 != 1 || options.rounds > MAX_ROUNDS {
+            return Err(format!(
+                "A match is played in an odd number of rounds (valid: 1-{})",
+                MAX_ROUNDS
+            ));
+        }
+
+        Ok(options)
+    }
+}
+
+fn find_opponent(
+    wanted: &Option<String>,
+    npc_res: &Res<NPCsResource>,
+    console_writer: &mut EventWriter<PrintConsoleEvent>,
+) -> Option<Opponent> {
+    if let Some(username) = wanted {
+        if let Some(npc) = npc_res
+            .npcs
+            .values()
+            .find(|npc| npc.username.to_lowercase() == *username)
+        {
+            return Some(Opponent::from_npc(npc));
+        }
+
+        console_writer.send(PrintConsoleEvent(format!(
+            "'{}' isn't online, looking for another player...",
+            username
+        )));
+    }
+
+    npc_res
+        .npcs
+        .values()
+        .choose(&mut rand::thread_rng())
+        .map(Opponent::from_npc)
+}
+
+// the opponent won't forget how you treated them
+pub fn update_relationship(
+    ttt_data: &TicTacToeData,
+    vuln_res: &mut ResMut<VulnerabilityResource>,
+    interaction: Interaction,
+) {
+    if let Some(opponent) = &ttt_data.opponent {
+        vuln_res
+            .relationships
+            .interact(&opponent.username, interaction);
+    }
+}
+
+pub fn display_tutorial(board: &Board) -> String {
     let mut res = String::from("\n\n\nTicTactToe Tutorial\n");
     res.push_str("-------------------\n");
 
-    res.push_str(
+    res.push_str(&format!(
         "
-The goal is to align 3 'X' the computer will try
-to align 3 'O'. You have to beat the computer!
-    
-The grid is made of 9 cells from 'A' to 'I'.
-
-   A | B | C
-   _________
-   D | E | F
-   _________
-   G | H | I
-    
-To play, type: 'place A' for example...
-    
+The goal is to align {} 'X' the computer will try
+to align {} 'O'. You have to beat the computer!
+Lines can be horizontal, vertical or diagonal.
+
+The grid is made of {}x{} cells, columns are letters
+and rows are numbers: cells go from A1 to {}.
+
+{}
+To play, type: 'place B2' for example...
+Made a mistake? Type 'undo' to take it back, {} times
+per match.
+
 Good luck!\n",
-    );
+        board.win_length,
+        board.win_length,
+        board.size,
+        board.size,
+        board.last_position_name(),
+        board.display(),
+        MAX_UNDOS,
+    ));
+
+    res
+}
+
+pub fn display_ultimate_tutorial(ultimate: &UltimateBoard) -> String {
+    let mut res = String::from("\n\n\nUltimate TicTactToe Tutorial\n");
+    res.push_str("----------------------------\n");
+
+    res.push_str(&format!(
+        "
+The grid is made of 9 boards (A1 to C3) of 9 cells
+(a1 to c3). Win 3 boards in a row to win the game!
+
+Where you play tells your opponent where to play
+next: a pawn in the cell c1 of any board sends
+them to the board C1. If that board is over, they
+can play in any open board.
+
+{}
+To play, type: 'place B2A1' for the cell A1 of the
+board B2... Made a mistake? Type 'undo', {} times
+per match.
+
+Good luck!\n",
+        ultimate.display(),
+        MAX_UNDOS,
+    ));
+
+    res
+}
+
+pub fn display_score(ttt_data: &TicTacToeData) -> String {
+    format!(
+        "Round {}/{} | Score: You {} - {} {}",
+        ttt_data.round_number,
+        ttt_data.rounds,
+        ttt_data.player_score,
+        ttt_data.opponent_score,
+        ttt_data.get_opponent_name()
+    )
+}
+
+pub fn display_replay(ttt_data: &TicTacToeData) -> String {
+    let mut res = String::from("---------------------[Replay]---------------------\n");
+
+    for (index, moves) in ttt_data.history.iter().enumerate() {
+        res.push_str(&format!("Round {}\n", index + 1));
+
+        for (turn, pair) in moves.chunks(2).enumerate() {
+            let notation: Vec<String> = pair
+                .iter()
+                .map(|m| {
+                    let pawn = if m.pawn == board::PLAYER { "X" } else { "O" };
+                    format!("{} {}", pawn, ttt_data.get_position_name(m.position))
+                })
+                .collect();
+            res.push_str(&format!("{:>3}. {}\n", turn + 1, notation.join("   ")));
+        }
+        res.push('\n');
+    }
 
     res
 }
 
+// Updates the score and moves on to the next round or ends the match
+fn end_round(
+    winner: usize,
+    cg_data: &mut ResMut<ConsoleGamesData>,
+    ttt_data: &mut ResMut<TicTacToeData>,
+    console_writer: &mut EventWriter<PrintConsoleEvent>,
+    vuln_res: &mut ResMut<VulnerabilityResource>,
+) {
+    match winner {
+        board::PLAYER => {
+            ttt_data.player_score += 1;
+            console_writer.send(PrintConsoleEvent(format!(
+                "You win round {}!",
+                ttt_data.round_number
+            )));
+        }
+        board::AI => {
+            ttt_data.opponent_score += 1;
+            console_writer.send(PrintConsoleEvent(format!(
+                "{} wins round {}...",
+                ttt_data.get_opponent_name(),
+                ttt_data.round_number
+            )));
+        }
+        _ => console_writer.send(PrintConsoleEvent(
+            "It's a tie... What are you doing ??!".to_string(),
+        )),
+    }
+    console_writer.send(PrintConsoleEvent(display_score(ttt_data)));
+
+    if ttt_data.opponent_score >= ttt_data.rounds_to_win() {
+        console_writer.send(PrintConsoleEvent(format!(
+            "{}: \"ez\"",
+            ttt_data.get_opponent_name()
+        )));
+        console_writer.send(PrintConsoleEvent("You lost like a *****".to_string()));
+        update_relationship(ttt_data, vuln_res, Interaction::Defeat);
+        cg_data.loaded_game = GameList::None;
+        ttt_data.reset();
+        *vuln_res
+            .bool_vulnerabilities
+            .get_mut(&BoolVulnerabilityType::TicTacToeLosing)
+            .unwrap() = true;
+    } else if ttt_data.player_score >= ttt_data.rounds_to_win() {
+        console_writer.send(PrintConsoleEvent(format!(
+            "CONGRATS!!! You beat {}",
+            ttt_data.get_opponent_name()
+        )));
+
+        if ttt_data.opponent_score == 0 && ttt_data.is_crushing_win() {
+            console_writer.send(PrintConsoleEvent(format!(
+                "{} leaves the game after such a crushing defeat...\nThey won't forget it.",
+                ttt_data.get_opponent_name()
+            )));
+            update_relationship(ttt_data, vuln_res, Interaction::CrushingWin);
+            console_writer.send(PrintConsoleEvent(
+                "Type 'replay' to see the match again or 'gg' to leave".to_string(),
+            ));
+        } else {
+            console_writer.send(PrintConsoleEvent(
+                "Type 'gloat' to rub it in, 'replay' to see the match again\nor 'gg' to leave like a good sport".to_string(),
+            ));
+        }
+        ttt_data.current_turn = TurnType::Finished;
+        ttt_data.waiting_for_input = true;
+    } else if ttt_data.round_number == ttt_data.rounds {
+        console_writer.send(PrintConsoleEvent(
+            "Nobody wins this match...\nType 'replay' to see the match again or 'gg' to leave"
+                .to_string(),
+        ));
+        ttt_data.current_turn = TurnType::Finished;
+        ttt_data.waiting_for_input = true;
+    } else {
+        ttt_data.start_round();
+    }
+}
+
 pub fn game_loop(
     mut cg_data: ResMut<ConsoleGamesData>,
     mut ttt_data: ResMut<TicTacToeData>,
     mut console_writer: EventWriter<PrintConsoleEvent>,
     mut vuln_res: ResMut<VulnerabilityResource>,
+    npc_res: Res<NPCsResource>,
 ) {
     if !ttt_data.has_seen_tutorial {
-        ttt_data.turn_number = 1;
-        console_writer.send(PrintConsoleEvent(display_tutorial()));
+        // the options were already checked when the game was launched
+        if let Ok(options) = MatchOptions::from_args(&cg_data.launch_args) {
+            ttt_data.board = Board::new(options.size, options.win_length);
+            ttt_data.rounds = options.rounds;
+            if options.ultimate {
+                ttt_data.ultimate = Some(UltimateBoard::default());
+            }
+            ttt_data.opponent = find_opponent(&options.opponent, &npc_res, &mut console_writer);
+        }
+
+        console_writer.send(PrintConsoleEvent(match &ttt_data.ultimate {
+            Some(ultimate) => display_ultimate_tutorial(ultimate),
+            None => display_tutorial(&ttt_data.board),
+        }));
+        if let Some(opponent) = &ttt_data.opponent {
+            console_writer.send(PrintConsoleEvent(opponent.display_intro()));
+        }
+        if ttt_data.rounds > 1 {
+            console_writer.send(PrintConsoleEvent(format!(
+                "Best of {}: first to {} rounds wins the match!\n",
+                ttt_data.rounds,
+                ttt_data.rounds_to_win()
+            )));
+        }
+        ttt_data.start_round();
         ttt_data.has_seen_tutorial = true;
     }
 
     if !ttt_data.waiting_for_input {
         console_writer.send(PrintConsoleEvent(format!(
-            "---------------------------\nTurn number: {}",
-            ttt_data.turn_number
+            "---------------------------\n{} | Turn number: {}",
+            display_score(&ttt_data),
+            ttt_data.turn_number()
         )));
         console_writer.send(PrintConsoleEvent(display_grid(&ttt_data)));
 
         // check if there is a winner
-        match ttt_data.has_winner() {
-            1 => {
-                console_writer.send(PrintConsoleEvent("CONGRATS!!! You won".to_string()));
-                cg_data.loaded_game = GameList::None;
-                ttt_data.reset();
-                return;
-            }
-            2 => {
-                console_writer.send(PrintConsoleEvent("You lost like a *****".to_string()));
-                cg_data.loaded_game = GameList::None;
-                ttt_data.reset();
-                *vuln_res
-                    .bool_vulnerabilities
-                    .get_mut(&BoolVulnerabilityType::TicTacToeLosing)
-                    .unwrap() = true;
-                return;
-            }
-            _ => (),
-        }
-
-        // check if pawns can be placed
-        if !ttt_data.has_space_available() {
-            console_writer.send(PrintConsoleEvent(
-                "It's a tie... What are you doing ??!".to_string(),
-            ));
-            cg_data.loaded_game = GameList::None;
-            ttt_data.reset();
+        let winner = ttt_data.has_winner();
+        if winner != board::EMPTY || !ttt_data.has_space_available() {
+            end_round(
+                winner,
+                &mut cg_data,
+                &mut ttt_data,
+                &mut console_writer,
+                &mut vuln_res,
+            );
             return;
         }
 
         match ttt_data.current_turn {
             TurnType::PlayerTurn => {
-                ttt_data.turn_number += 1;
-                console_writer.send(PrintConsoleEvent(
-                    "It's your turn to play [A-I]:".to_string(),
-                ));
+                console_writer.send(PrintConsoleEvent(format!(
+                    "It's your turn to play [{}]:",
+                    ttt_data.valid_positions()
+                )));
             }
 
             TurnType::AITurn => {
-                // pick a valid position
-                loop {
-                    let index = rand::thread_rng().gen_range(0..9);
-                    let letter = ["a", "b", "c", "d", "e", "f", "g", "h", "i"][index];
-
-                    let (i, j) = TicTacToeData::get_position(letter).unwrap();
-                    if ttt_data.grid[i][j] == 0 {
-                        ttt_data.grid[i][j] = 2;
-                        break;
-                    }
+                if let Some(position) = ttt_data.pick_ai_move() {
+                    ttt_data.play_move(board::AI, position);
                 }
 
                 ttt_data.current_turn = TurnType::PlayerTurn;
                 ttt_data.waiting_for_input = false;
                 return;
             }
+
+            TurnType::Finished => (),
         }
         ttt_data.waiting_for_input = true;
     }
@@ -206,58 +611,47 @@ pub fn play_position(
     ttt_data: &mut ResMut<TicTacToeData>,
     console_writer: &mut EventWriter<PrintConsoleEvent>,
 ) {
-    if ttt_data.waiting_for_input {
-        if let Some(position) = TicTacToeData::get_position(place) {
-            let (i, j) = position;
-            match ttt_data.grid[i][j] {
-                1 => console_writer.send(PrintConsoleEvent(
-                    "You already placed a pawn here".to_string(),
-                )),
-                2 => console_writer.send(PrintConsoleEvent(
-                    "You can't place a pawn on top of your opponent's".to_string(),
-                )),
-                _ => {
+    if ttt_data.waiting_for_input && ttt_data.current_turn == TurnType::PlayerTurn {
+        if let Some(position) = ttt_data.get_position(place) {
+            let checked_move = match &ttt_data.ultimate {
+                Some(ultimate) => ultimate.check_move(position),
+                None => match ttt_data.board.grid[position.0][position.1] {
+                    board::PLAYER => Err("You already placed a pawn here".to_string()),
+                    board::AI => {
+                        Err("You can't place a pawn on top of your opponent's".to_string())
+                    }
+                    _ => Ok(()),
+                },
+            };
+
+            match checked_move {
+                Ok(()) => {
                     console_writer.send(PrintConsoleEvent(format!(
-                        "You place a pawn at position ({}, {})",
-                        i, j
+                        "You place a pawn at position {}",
+                        ttt_data.get_position_name(position)
                     )));
-                    ttt_data.grid[i][j] = 1;
+                    ttt_data.play_move(board::PLAYER, position);
                     ttt_data.current_turn = TurnType::AITurn;
                     ttt_data.waiting_for_input = false;
                 }
+                Err(msg) => console_writer.send(PrintConsoleEvent(msg)),
             }
         } else {
             console_writer.send(PrintConsoleEvent(format!(
-                "'{}' is not a valid position (valid: [A-I])",
-                place
+                "'{}' is not a valid position (valid: [{}])",
+                place,
+                ttt_data.valid_positions()
             )));
         }
     }
 }
 
 pub fn display_grid(ttt_data: &ResMut<TicTacToeData>) -> String {
-    let mut res = String::from("Current grid | Positions : \n");
-
-    res.push_str(&format!(
-        "{} | {} | {}        A | B | C \n",
-        ttt_data.get_pawn(0, 0),
-        ttt_data.get_pawn(0, 1),
-        ttt_data.get_pawn(0, 2),
-    ));
-    res.push_str("_________       _________\n");
-    res.push_str(&format!(
-        "{} | {} | {}        D | E | F \n",
-        ttt_data.get_pawn(1, 0),
-        ttt_data.get_pawn(1, 1),
-        ttt_data.get_pawn(1, 2),
-    ));
-    res.push_str("_________       _________\n");
-    res.push_str(&format!(
-        "{} | {} | {}        G | H | I\n\n",
-        ttt_data.get_pawn(2, 0),
-        ttt_data.get_pawn(2, 1),
-        ttt_data.get_pawn(2, 2),
-    ));
+    let mut res = String::from("Current grid:\n");
+    match &ttt_data.ultimate {
+        Some(ultimate) => res.push_str(&ultimate.display()),
+        None => res.push_str(&ttt_data.board.display()),
+    }
 
     res
 }
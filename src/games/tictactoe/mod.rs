@@ -1,9 +1,12 @@
+mod board;
 mod commands;
 mod game;
+mod opponent;
+mod ultimate;
 
 use bevy::{ecs::schedule::ShouldRun, prelude::*};
 
-use crate::{games::GameList, states::GameState};
+use crate::{console::event::PrintConsoleEvent, games::GameList, states::GameState};
 
 use super::ConsoleGamesData;
 
@@ -22,7 +25,19 @@ impl Plugin for TicTacToePlugin {
     }
 }
 
-pub fn start_game(cg_data: &mut ResMut<ConsoleGamesData>) {
+pub fn start_game(
+    cg_data: &mut ResMut<ConsoleGamesData>,
+    console_writer: &mut EventWriter<PrintConsoleEvent>,
+) {
+    if let Err(msg) = game::MatchOptions::from_args(&cg_data.launch_args) {
+        console_writer.send(PrintConsoleEvent(msg));
+        console_writer.send(PrintConsoleEvent(
+            "Usage: play tictactoe [--size N] [--align K] [--rounds N]\n[--vs <username>] [--variant classic|ultimate]"
+                .to_string(),
+        ));
+        return;
+    }
+
     cg_data.loaded_game = GameList::TicTacToe;
     #[cfg(debug_assertions)]
     info!("Starting tictactoe game");
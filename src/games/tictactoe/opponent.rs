@@ -0,0 +1,94 @@
+use crate::npcs::NPCData;
+
+use super::board::AIProfile;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayStyle {
+    Casual,
+    Aggressive,
+    Defensive,
+    Tryhard,
+}
+
+impl PlayStyle {
+    // the style only depends on who the npc is so it never changes
+    pub fn from_npc(npc: &NPCData) -> PlayStyle {
+        let seed = npc.username.bytes().map(usize::from).sum::<usize>() + npc.sprite_id;
+
+        match seed % 4 {
+            0 => PlayStyle::Casual,
+            1 => PlayStyle::Aggressive,
+            2 => PlayStyle::Defensive,
+            _ => PlayStyle::Tryhard,
+        }
+    }
+
+    pub fn get_profile(&self) -> AIProfile {
+        match self {
+            PlayStyle::Casual => AIProfile {
+                mistake_ratio: 2,
+                attack_weight: 1,
+                defense_weight: 1,
+            },
+            PlayStyle::Aggressive => AIProfile {
+                mistake_ratio: 5,
+                attack_weight: 3,
+                defense_weight: 1,
+            },
+            PlayStyle::Defensive => AIProfile {
+                mistake_ratio: 5,
+                attack_weight: 1,
+                defense_weight: 3,
+            },
+            PlayStyle::Tryhard => AIProfile {
+                mistake_ratio: 10,
+                attack_weight: 2,
+                defense_weight: 2,
+            },
+        }
+    }
+
+    pub fn to_display_str(&self) -> &str {
+        match self {
+            PlayStyle::Casual => "casual",
+            PlayStyle::Aggressive => "aggressive",
+            PlayStyle::Defensive => "defensive",
+            PlayStyle::Tryhard => "tryhard",
+        }
+    }
+
+    pub fn get_intro(&self) -> &str {
+        match self {
+            PlayStyle::Casual => "\"hey. sure, one game, I'm just chilling\"",
+            PlayStyle::Aggressive => "\"I'm going straight for the win, watch me\"",
+            PlayStyle::Defensive => "\"You won't get a single line past me\"",
+            PlayStyle::Tryhard => "\"I've studied this game for years. Prepare yourself.\"",
+        }
+    }
+}
+
+pub struct Opponent {
+    pub username: String,
+    pub style: PlayStyle,
+}
+
+impl Opponent {
+    pub fn from_npc(npc: &NPCData) -> Opponent {
+        Opponent {
+            username: npc.username.clone(),
+            style: PlayStyle::from_npc(npc),
+        }
+    }
+
+    pub fn display_intro(&self) -> String {
+        let mut res = String::from("------------------[Match Found]-------------------\n");
+        res.push_str(&format!(
+            "Your opponent: {} ({} player)\n",
+            self.username,
+            self.style.to_display_str()
+        ));
+        res.push_str(&format!("{}: {}\n", self.username, self.style.get_intro()));
+
+        res
+    }
+}
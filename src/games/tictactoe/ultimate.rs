@@ -0,0 +1,265 @@
+use rand::{prelude::SliceRandom, Rng};
+
+use super::board::{self, AIProfile, Board};
+
+// the ultimate grid is made of 3x3 boards of 3x3 cells
+pub const BOARD_SIZE: usize = 3;
+pub const GRID_SIZE: usize = BOARD_SIZE * BOARD_SIZE;
+
+// how a finished board is drawn, row by row
+const PLAYER_BOARD_ART: [&str; 3] = ["X . X", ". X .", "X . X"];
+const AI_BOARD_ART: [&str; 3] = ["O O O", "O . O", "O O O"];
+const DRAW_BOARD_ART: [&str; 3] = ["# # #", "# # #", "# # #"];
+
+/// Ultimate TicTacToe: every move sends the opponent to the board
+/// matching the cell that was just played. Positions are (row, column)
+/// in the whole 9x9 grid.
+#[derive(Debug, Clone)]
+pub struct UltimateBoard {
+    // the small boards from A1 to C3, row by row
+    pub boards: Vec<Board>,
+    pub last_move: Option<(usize, usize)>,
+}
+
+impl Default for UltimateBoard {
+    fn default() -> Self {
+        UltimateBoard {
+            boards: vec![Board::default(); GRID_SIZE],
+            last_move: None,
+        }
+    }
+}
+
+impl UltimateBoard {
+    // index of the small board and position inside of it
+    fn split((r, c): (usize, usize)) -> (usize, (usize, usize)) {
+        (
+            r / BOARD_SIZE * BOARD_SIZE + c / BOARD_SIZE,
+            (r % BOARD_SIZE, c % BOARD_SIZE),
+        )
+    }
+
+    fn join(index: usize, (i, j): (usize, usize)) -> (usize, usize) {
+        (
+            index / BOARD_SIZE * BOARD_SIZE + i,
+            index % BOARD_SIZE * BOARD_SIZE + j,
+        )
+    }
+
+    /// Parses "b2a1" as the cell A1 of the board B2
+    pub fn get_position(&self, coords: &str) -> Option<(usize, usize)> {
+        let small_board = Board::default();
+        let (bi, bj) = small_board.get_position(coords.get(..2)?)?;
+        let position = small_board.get_position(coords.get(2..)?)?;
+
+        Some(UltimateBoard::join(bi * BOARD_SIZE + bj, position))
+    }
+
+    pub fn get_position_name(position: (usize, usize)) -> String {
+        let (index, (i, j)) = UltimateBoard::split(position);
+        format!(
+            "{}{}",
+            Board::get_position_name(index / BOARD_SIZE, index % BOARD_SIZE),
+            Board::get_position_name(i, j)
+        )
+    }
+
+    pub fn get_cell(&self, position: (usize, usize)) -> usize {
+        let (index, (i, j)) = UltimateBoard::split(position);
+        self.boards[index].grid[i][j]
+    }
+
+    pub fn set_cell(&mut self, position: (usize, usize), pawn: usize) {
+        let (index, (i, j)) = UltimateBoard::split(position);
+        self.boards[index].grid[i][j] = pawn;
+    }
+
+    // the 3x3 board of who won each small board
+    pub fn meta_board(&self) -> Board {
+        let mut meta = Board::default();
+        for (index, small_board) in self.boards.iter().enumerate() {
+            meta.grid[index / BOARD_SIZE][index % BOARD_SIZE] = match small_board.has_winner() {
+                board::EMPTY if small_board.has_space_available() => board::EMPTY,
+                board::EMPTY => board::DRAW,
+                winner => winner,
+            };
+        }
+        meta
+    }
+
+    /// The board the next pawn has to go in, None if any open board will do
+    pub fn forced_board(&self) -> Option<usize> {
+        let (_, (i, j)) = UltimateBoard::split(self.last_move?);
+        let target = i * BOARD_SIZE + j;
+
+        if self.meta_board().grid[i][j] == board::EMPTY {
+            Some(target)
+        } else {
+            None
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<(usize, usize)> {
+        let meta = self.meta_board();
+        let forced_board = self.forced_board();
+
+        let mut res = Vec::new();
+        for (index, small_board) in self.boards.iter().enumerate() {
+            if meta.grid[index / BOARD_SIZE][index % BOARD_SIZE] != board::EMPTY
+                || matches!(forced_board, Some(forced) if forced != index)
+            {
+                continue;
+            }
+
+            for position in small_board.free_cells() {
+                res.push(UltimateBoard::join(index, position));
+            }
+        }
+        res
+    }
+
+    pub fn check_move(&self, position: (usize, usize)) -> Result<(), String> {
+        let (index, _) = UltimateBoard::split(position);
+        let board_name = Board::get_position_name(index / BOARD_SIZE, index % BOARD_SIZE);
+
+        if self.meta_board().grid[index / BOARD_SIZE][index % BOARD_SIZE] != board::EMPTY {
+            return Err(format!("The board {} is already over", board_name));
+        }
+        if let Some(forced) = self.forced_board() {
+            if forced != index {
+                return Err(format!(
+                    "You have to play in the board {}",
+                    Board::get_position_name(forced / BOARD_SIZE, forced % BOARD_SIZE)
+                ));
+            }
+        }
+        if self.get_cell(position) != board::EMPTY {
+            return Err("There is already a pawn here".to_string());
+        }
+
+        Ok(())
+    }
+
+    pub fn has_winner(&self) -> usize {
+        self.meta_board().has_winner()
+    }
+
+    pub fn has_space_available(&self) -> bool {
+        !self.legal_moves().is_empty()
+    }
+
+    pub fn boards_won(&self, pawn: usize) -> usize {
+        self.meta_board().count_pawns(pawn)
+    }
+
+    // Small board tactics first, then the cell score of the small board.
+    // Sending the player to a board they can win right away is avoided.
+    fn move_score(&self, meta: &Board, position: (usize, usize), profile: &AIProfile) -> i64 {
+        let (index, (i, j)) = UltimateBoard::split(position);
+        let (bi, bj) = (index / BOARD_SIZE, index % BOARD_SIZE);
+        let small_board = &self.boards[index];
+
+        let mut score = small_board.cell_score(i, j, profile) as i64;
+
+        if small_board.is_winning_move(i, j, board::AI) {
+            score += 1000 * profile.attack_weight as i64;
+            if meta.is_winning_move(bi, bj, board::AI) {
+                score += 100_000;
+            }
+        } else if small_board.is_winning_move(i, j, board::PLAYER) {
+            score += 500 * profile.defense_weight as i64;
+            if meta.is_winning_move(bi, bj, board::PLAYER) {
+                score += 50_000;
+            }
+        }
+
+        // the next board of the player
+        let target = i * BOARD_SIZE + j;
+        if meta.grid[i][j] != board::EMPTY {
+            score -= 200;
+        } else if self.boards[target]
+            .find_winning_move(board::PLAYER)
+            .is_some()
+        {
+            score -= 400 * profile.defense_weight as i64;
+        }
+
+        score
+    }
+
+    pub fn pick_ai_move(&self, profile: &AIProfile) -> Option<(usize, usize)> {
+        let legal_moves = self.legal_moves();
+
+        // nobody's perfect
+        if rand::thread_rng().gen_ratio(1, profile.mistake_ratio) {
+            return legal_moves.choose(&mut rand::thread_rng()).copied();
+        }
+
+        let meta = self.meta_board();
+        let scores: Vec<i64> = legal_moves
+            .iter()
+            .map(|position| self.move_score(&meta, *position, profile))
+            .collect();
+        let best_score = *scores.iter().max()?;
+
+        let best_moves: Vec<(usize, usize)> = legal_moves
+            .into_iter()
+            .zip(scores)
+            .filter(|(_, score)| *score == best_score)
+            .map(|(position, _)| position)
+            .collect();
+
+        best_moves.choose(&mut rand::thread_rng()).copied()
+    }
+
+    fn display_board_row(&self, meta: &Board, index: usize, i: usize) -> String {
+        match meta.grid[index / BOARD_SIZE][index % BOARD_SIZE] {
+            board::PLAYER => PLAYER_BOARD_ART[i].to_string(),
+            board::AI => AI_BOARD_ART[i].to_string(),
+            board::DRAW => DRAW_BOARD_ART[i].to_string(),
+            _ => (0..BOARD_SIZE)
+                .map(|j| match self.boards[index].grid[i][j] {
+                    board::EMPTY => ".",
+                    _ => self.boards[index].get_pawn(i, j),
+                })
+                .collect::<Vec<&str>>()
+                .join(" "),
+        }
+    }
+
+    pub fn display(&self) -> String {
+        let meta = self.meta_board();
+
+        let mut res = String::from("        A       B       C\n");
+        res.push_str("      a b c | a b c | a b c\n");
+
+        for bi in 0..BOARD_SIZE {
+            for i in 0..BOARD_SIZE {
+                let rows: Vec<String> = (0..BOARD_SIZE)
+                    .map(|bj| self.display_board_row(&meta, bi * BOARD_SIZE + bj, i))
+                    .collect();
+
+                // the number of the board is on the middle row
+                if i == BOARD_SIZE / 2 {
+                    res.push_str(&format!(" {}  {} {}\n", bi + 1, i + 1, rows.join(" | ")));
+                } else {
+                    res.push_str(&format!("    {} {}\n", i + 1, rows.join(" | ")));
+                }
+            }
+
+            if bi + 1 < BOARD_SIZE {
+                res.push_str("      ------+-------+------\n");
+            }
+        }
+
+        res.push_str(&match self.forced_board() {
+            Some(index) => format!(
+                "Next board: {}\n",
+                Board::get_position_name(index / BOARD_SIZE, index % BOARD_SIZE)
+            ),
+            None => "Next board: any open board\n".to_string(),
+        });
+
+        res
+    }
+}
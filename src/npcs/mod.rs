@@ -1,9 +1,13 @@
+mod relationships;
+
 use bevy::prelude::*;
 use rand::seq::SliceRandom;
 use ron::de::from_bytes;
 use serde::Deserialize;
 use std::collections::HashMap;
 
+pub use self::relationships::{Interaction, Relationships};
+
 pub const NPC_COUNT: usize = 3;
 pub struct NPCsPlugin;
 
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+// everyone starts at 0, the scores stay between these bounds
+pub const MIN_SCORE: i32 = -10;
+pub const MAX_SCORE: i32 = 10;
+// at or below it, the NPC comes after the player in the real world
+pub const HOSTILE_THRESHOLD: i32 = -3;
+pub const FRIEND_THRESHOLD: i32 = 3;
+
+/// Something the player did to an NPC, in a game or in a chat
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interaction {
+    Talk,
+    Insult,
+    // walked past them without a word
+    Skip,
+    // ran from a fight or was rude while they were around
+    Disappoint,
+    Gift,
+    Apology,
+    // said 'gg' at the end of a match
+    GoodGame,
+    Gloat,
+    CrushingWin,
+    // they won against the player
+    Defeat,
+    // the player beat them when they came for revenge in the labyrinth
+    RivalBeaten,
+    RivalWon,
+}
+
+impl Interaction {
+    pub fn get_change(self) -> i32 {
+        match self {
+            Interaction::Talk => 1,
+            Interaction::Insult => -3,
+            Interaction::Skip => -1,
+            Interaction::Disappoint => -1,
+            Interaction::Gift => 2,
+            Interaction::Apology => 2,
+            Interaction::GoodGame => 1,
+            Interaction::Gloat => -3,
+            Interaction::CrushingWin => -3,
+            Interaction::Defeat => 1,
+            Interaction::RivalBeaten => 2,
+            Interaction::RivalWon => -2,
+        }
+    }
+}
+
+/// How much each NPC likes the player
+#[derive(Debug, Default)]
+pub struct Relationships {
+    scores: HashMap<String, i32>,
+}
+
+impl Relationships {
+    pub fn get(&self, username: &str) -> i32 {
+        self.scores.get(username).copied().unwrap_or(0)
+    }
+
+    fn set(&mut self, username: &str, score: i32) -> i32 {
+        let score = score.clamp(MIN_SCORE, MAX_SCORE);
+        self.scores.insert(username.to_string(), score);
+        score
+    }
+
+    /// Returns the new score
+    pub fn interact(&mut self, username: &str, interaction: Interaction) -> i32 {
+        self.change(username, interaction.get_change())
+    }
+
+    pub fn change(&mut self, username: &str, amount: i32) -> i32 {
+        self.set(username, self.get(username) + amount)
+    }
+
+    pub fn make_hostile(&mut self, username: &str) {
+        self.set(username, self.get(username).min(HOSTILE_THRESHOLD));
+    }
+
+    pub fn make_friend(&mut self, username: &str) {
+        self.set(username, self.get(username).max(FRIEND_THRESHOLD));
+    }
+
+    pub fn is_hostile(&self, username: &str) -> bool {
+        self.get(username) <= HOSTILE_THRESHOLD
+    }
+
+    pub fn is_friend(&self, username: &str) -> bool {
+        self.get(username) >= FRIEND_THRESHOLD
+    }
+
+    /// The NPCs that threaten the player, sorted by name
+    pub fn get_hostiles(&self) -> Vec<&str> {
+        let mut hostiles: Vec<&str> = self
+            .scores
+            .keys()
+            .filter(|username| self.is_hostile(username))
+            .map(|username| username.as_str())
+            .collect();
+        hostiles.sort_unstable();
+        hostiles
+    }
+
+    /// 0 if the NPC doesn't mind the player, from 0.5 at the threshold
+    /// to 1 for the ones who hate them the most
+    pub fn get_hostility(&self, username: &str) -> f32 {
+        let score = self.get(username);
+        if score > HOSTILE_THRESHOLD {
+            return 0.0;
+        }
+
+        0.5 + 0.5 * (HOSTILE_THRESHOLD - score) as f32 / (HOSTILE_THRESHOLD - MIN_SCORE) as f32
+    }
+
+    pub fn get_total_hostility(&self) -> f32 {
+        self.scores
+            .keys()
+            .map(|username| self.get_hostility(username))
+            .sum()
+    }
+
+    pub fn to_display(&self, username: &str) -> String {
+        let score = self.get(username);
+        let feeling = if score <= HOSTILE_THRESHOLD * 2 {
+            "Hates you"
+        } else if score <= HOSTILE_THRESHOLD {
+            "Hostile"
+        } else if score >= FRIEND_THRESHOLD * 2 {
+            "Best friend"
+        } else if score >= FRIEND_THRESHOLD {
+            "Friendly"
+        } else {
+            "Neutral"
+        };
+
+        format!("{} ({})", feeling, score)
+    }
+}
@@ -2,7 +2,7 @@ use crate::misc::{
     day_cycle::DayCycleResource,
     game_over::{GameOverData, GameOverReason},
 };
-use crate::npcs::NPCsResource;
+use crate::npcs::{NPCsResource, Relationships};
 use crate::states::GameState;
 use crate::{
     apartment::{
@@ -57,7 +57,7 @@ impl Plugin for VulnerabilityPlugin {
             lockpicking_status: LockpickingStatus::None,
             lockpicking_audio_timer: Timer::from_seconds(2.0, true),
             lockpicking_search_timer: Timer::from_seconds(LOCKPICK_SEARCH_TIME, true),
-            enemies: Vec::new(),
+            relationships: Relationships::default(),
             is_hiding: false,
         })
         .add_system_set(
@@ -138,7 +138,7 @@ pub struct VulnerabilityResource {
     pub lockpicking_status: LockpickingStatus,
     pub lockpicking_search_timer: Timer,
     pub is_hiding: bool,
-    pub enemies: Vec<String>, // append enemies made in game to this
+    pub relationships: Relationships, // how the NPCs met in game feel about the player
 }
 
 #[derive(Debug)]
@@ -172,8 +172,9 @@ pub fn update_vulnerability_factor_system(
             .sin())
         * (1.0 - DIFFICULTY)
         + DIFFICULTY;
-    let enemies_factor =
-        vulnerability_resource.enemies.len() as f32 / npcs_resource.npcs.len() as f32;
+    // the more the NPCs hate the player, the more likely they come
+    let enemies_factor = vulnerability_resource.relationships.get_total_hostility()
+        / npcs_resource.npcs.len() as f32;
     vulnerability_resource.vulnerability_factor = time_of_day_factor * enemies_factor;
 }
 
@@ -188,7 +189,7 @@ pub fn update_timed_vulnerabilities_system(
     audio: Res<Audio>,
     mut go_data: ResMut<GameOverData>,
 ) {
-    let number_of_enemies = vulnerability_resource.enemies.len();
+    let number_of_enemies = vulnerability_resource.relationships.get_hostiles().len();
     for (v_type, v_timer) in vulnerability_resource.timed_vulnerabilities.iter_mut() {
         match v_type {
             TimedVulnerabilityType::ConsoleDoorLeftOpen => {
@@ -360,7 +361,8 @@ pub fn lockpicking_system(
                             * vulnerability_resource.vulnerability_factor;
                         info!(
                             "enemies: {:?} roll: {}",
-                            vulnerability_resource.enemies, rand_val
+                            vulnerability_resource.relationships.get_hostiles(),
+                            rand_val
                         );
                         if rand_val > 0.25 {
                             // start lockpicking event